@@ -336,5 +336,351 @@ pub fn bench_json_serializing(c: &mut Criterion) {
 
     group.finish();
 }
-criterion_group!(benches, bench_encoding, bench_json_serializing);
+#[derive(Serialize)]
+struct Article<'a> {
+    title: &'a str,
+    tags: Vec<&'a str>,
+    paragraphs: Vec<&'a str>,
+}
+
+pub fn bench_string_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("String-Heavy Payload");
+
+    // Mostly text with scattered characters that need encoding, so the percent-
+    // encoder alternates between short unencoded runs and escapes.
+    let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. \
+        Sed do eiusmod tempor incididunt (ut labore) & dolore magna aliqua: \
+        \"Ut enim ad minim veniam\" -- quis nostrud exercitation/ullamco!";
+    let article = Article {
+        title: "A fairly long title, with spaces & punctuation?",
+        tags: vec!["rust", "serde", "form encoding", "percent-encoding"],
+        paragraphs: vec![paragraph; 16],
+    };
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| {
+            serde_metaform::to_string(black_box(&article)).unwrap();
+        });
+    });
+
+    // Every write goes through `fmt::Formatter`, i.e. a virtual call per write.
+    group.bench_function("display", |b| {
+        b.iter(|| {
+            black_box(&article);
+            serde_metaform::display(&article).to_string();
+        });
+    });
+
+    group.finish();
+}
+
+#[derive(Serialize)]
+struct Document {
+    id: u32,
+    sections: Vec<String>,
+}
+
+pub fn bench_long_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Long String Values");
+
+    // Nested strings are JSON-escaped and percent-encoded, so quotes, backslashes
+    // and newlines take the slowest path through the writer.
+    let section = "The \"quick\" brown fox jumps over the lazy dog.\n\
+        C:\\path\\to\\file\ttabs, commas & ampersands; unicode: caf\u{e9} \u{1F600}\n"
+        .repeat(64);
+    let document = Document {
+        id: 1,
+        sections: vec![section; 8],
+    };
+    let len: usize = document.sections.iter().map(String::len).sum();
+    group.throughput(criterion::Throughput::Bytes(len as u64));
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| {
+            serde_metaform::to_string(black_box(&document)).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+pub fn bench_quote_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Quote-Heavy String");
+
+    // Almost every character needs both JSON escaping and percent-encoding.
+    let document = Document {
+        id: 1,
+        sections: vec![r#""\"a\"""#.repeat(512)],
+    };
+    group.throughput(criterion::Throughput::Bytes(
+        document.sections[0].len() as u64
+    ));
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| {
+            serde_metaform::to_string(black_box(&document)).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+pub fn bench_homogeneous_array(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Homogeneous Array");
+
+    // 10,000 small objects. Every element and every key goes through the
+    // per-element writer setup, so wrapper overhead would show up here.
+    let rows: Vec<Row> = (0..10_000)
+        .map(|_| Row {
+            id: "row_id",
+            title: "Row title",
+            description: "A \"short\" description",
+        })
+        .collect();
+
+    group.bench_function("serde_json", |b| {
+        b.iter(|| serde_json_to_string_percent_encoded(black_box(&rows)));
+    });
+
+    group.bench_function("crate::json", |b| {
+        b.iter(|| crate_json_to_string_percent_encoded(black_box(&rows)));
+    });
+
+    group.finish();
+}
+
+#[derive(Serialize)]
+struct Delivery {
+    message_id: u64,
+    recipient_type: &'static str,
+    messaging_product: &'static str,
+    created_at: u64,
+    #[serde(rename = "status[code]")]
+    status_code: u16,
+    priority: u8,
+    attempts: u8,
+    delivered: bool,
+}
+
+pub fn bench_field_keys(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Struct Field Keys");
+
+    // Small scalar values, so the time goes mostly into writing the keys.
+    let delivery = Delivery {
+        message_id: 1,
+        recipient_type: "individual",
+        messaging_product: "whatsapp",
+        created_at: 1_700_000_000,
+        status_code: 200,
+        priority: 1,
+        attempts: 3,
+        delivered: true,
+    };
+    let mut out = String::with_capacity(256);
+
+    group.bench_function("to_writer", |b| {
+        b.iter(|| {
+            out.clear();
+            serde_metaform::to_writer(&mut out, black_box(&delivery)).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+pub fn bench_display(c: &mut Criterion) {
+    use std::fmt::Write as _;
+
+    let mut group = c.benchmark_group("Display Wrapper");
+
+    // Many small writes, so the cost of going through `fmt::Formatter` for each
+    // of them isn't hidden behind encoding work.
+    let delivery = Delivery {
+        message_id: 1,
+        recipient_type: "individual",
+        messaging_product: "whatsapp",
+        created_at: 1_700_000_000,
+        status_code: 200,
+        priority: 1,
+        attempts: 3,
+        delivered: true,
+    };
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| serde_metaform::to_string(black_box(&delivery)).unwrap());
+    });
+
+    group.bench_function("format!(display)", |b| {
+        b.iter(|| format!("{}", serde_metaform::display(black_box(&delivery))));
+    });
+
+    // Without allocating, to compare the writes alone.
+    let mut out = String::with_capacity(256);
+    group.bench_function("to_writer", |b| {
+        b.iter(|| {
+            out.clear();
+            serde_metaform::to_writer(&mut out, black_box(&delivery)).unwrap();
+        });
+    });
+
+    group.bench_function("write!(display)", |b| {
+        b.iter(|| {
+            out.clear();
+            write!(out, "{}", serde_metaform::display(black_box(&delivery))).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+#[derive(Serialize)]
+struct Samples {
+    integers: Vec<i64>,
+    floats: Vec<f64>,
+}
+
+pub fn bench_numbers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Numeric Arrays");
+
+    let samples = Samples {
+        integers: (0..10_000).map(|i| i * 7919 - 5_000_000).collect(),
+        floats: (0..10_000).map(|i| f64::from(i) * 0.37 - 1000.0).collect(),
+    };
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| {
+            serde_metaform::to_string(black_box(&samples)).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+/// A UUID that serializes through `collect_str`, like most `Display` types do.
+/// Its hyphenated form is written with several small `write_str` calls.
+struct Uuid(u128);
+
+impl std::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let n = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            n >> 96,
+            (n >> 80) & 0xffff,
+            (n >> 64) & 0xffff,
+            (n >> 48) & 0xffff,
+            n & 0xffff_ffff_ffff
+        )
+    }
+}
+
+impl Serialize for Uuid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Serialize)]
+struct Identifiers {
+    id00: Uuid,
+    id01: Uuid,
+    id02: Uuid,
+    id03: Uuid,
+    id04: Uuid,
+    id05: Uuid,
+    id06: Uuid,
+    id07: Uuid,
+    id08: Uuid,
+    id09: Uuid,
+    id10: Uuid,
+    id11: Uuid,
+    id12: Uuid,
+    id13: Uuid,
+    id14: Uuid,
+    id15: Uuid,
+    id16: Uuid,
+    id17: Uuid,
+    id18: Uuid,
+    id19: Uuid,
+}
+
+pub fn bench_collect_str(c: &mut Criterion) {
+    let mut group = c.benchmark_group("UUID Fields");
+
+    let uuid = |i: u128| Uuid(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8 ^ (i * 0x1234_5678_9abc));
+    let ids = Identifiers {
+        id00: uuid(0),
+        id01: uuid(1),
+        id02: uuid(2),
+        id03: uuid(3),
+        id04: uuid(4),
+        id05: uuid(5),
+        id06: uuid(6),
+        id07: uuid(7),
+        id08: uuid(8),
+        id09: uuid(9),
+        id10: uuid(10),
+        id11: uuid(11),
+        id12: uuid(12),
+        id13: uuid(13),
+        id14: uuid(14),
+        id15: uuid(15),
+        id16: uuid(16),
+        id17: uuid(17),
+        id18: uuid(18),
+        id19: uuid(19),
+    };
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| {
+            serde_metaform::to_string(black_box(&ids)).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+#[derive(Serialize)]
+struct Blob {
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+    nested: Vec<serde_bytes::ByteBuf>,
+}
+
+pub fn bench_byte_array(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Byte Array");
+
+    // 256 KiB, written as a JSON array of numbers, once at the top level and
+    // once nested (where the array goes through the escaping writer).
+    let data: Vec<u8> = (0..256 * 1024).map(|i: u32| (i * 31 % 256) as u8).collect();
+    let blob = Blob {
+        nested: vec![serde_bytes::ByteBuf::from(data.clone())],
+        data,
+    };
+    group.throughput(criterion::Throughput::Bytes(2 * 256 * 1024));
+
+    group.bench_function("to_string", |b| {
+        b.iter(|| {
+            serde_metaform::to_string(black_box(&blob)).unwrap();
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_encoding,
+    bench_json_serializing,
+    bench_string_heavy,
+    bench_long_strings,
+    bench_quote_heavy,
+    bench_numbers,
+    bench_homogeneous_array,
+    bench_field_keys,
+    bench_display,
+    bench_collect_str,
+    bench_byte_array
+);
 criterion_main!(benches);
@@ -0,0 +1,96 @@
+#!/usr/bin/env bash
+# Compares build time and code size with and without the `dyn-write` feature.
+#
+# Generates a throwaway binary crate with 40 nested payload types, each
+# serialized with `to_string` and `display`, then builds it once per feature
+# set. Only the generated crate is timed: dependencies are built first.
+#
+# Usage: benches/dyn_write_size.sh [number of payload types]
+set -euo pipefail
+
+types="${1:-40}"
+root="$(cd "$(dirname "$0")/.." && pwd)"
+dir="$(mktemp -d)"
+trap 'rm -rf "$dir"' EXIT
+# Incremental builds would reuse most of the work on the timed rebuild.
+export CARGO_INCREMENTAL=0
+
+mkdir "$dir/src"
+cat >"$dir/Cargo.toml" <<EOF
+[package]
+name = "dyn-write-size"
+version = "0.0.0"
+edition = "2024"
+publish = false
+
+[dependencies]
+serde = { version = "1", features = ["derive"] }
+serde-metaform = { path = "$root" }
+
+[features]
+dyn-write = ["serde-metaform/dyn-write"]
+
+[workspace]
+EOF
+
+{
+    echo "use std::collections::BTreeMap;"
+    echo "use std::hint::black_box;"
+    echo "use serde::Serialize;"
+    for i in $(seq 1 "$types"); do
+        cat <<EOF
+
+#[derive(Serialize, Default)]
+struct Item$i { x: f64, flag: bool, tags: Vec<String> }
+
+#[derive(Serialize, Default)]
+struct Inner$i { a: u32, b: Vec<String>, item: Item$i, items: Vec<Item$i> }
+
+#[derive(Serialize, Default)]
+struct Payload$i {
+    id: u64,
+    name: String,
+    inner: Inner$i,
+    meta: Option<BTreeMap<String, Item$i>>,
+    pairs: Vec<(String, Option<Inner$i>)>,
+}
+EOF
+    done
+    echo
+    echo "fn main() {"
+    for i in $(seq 1 "$types"); do
+        echo "    let p = black_box(Payload$i::default());"
+        echo "    println!(\"{}\", serde_metaform::to_string(&p).unwrap());"
+        echo "    println!(\"{}\", serde_metaform::display(&p));"
+    done
+    echo "}"
+} >"$dir/src/main.rs"
+
+# Seconds taken to rebuild only the generated crate.
+build_time() {
+    cargo build --quiet --manifest-path "$dir/Cargo.toml" "$@"
+    # Cargo compares mtimes, so make sure the touch is newer.
+    sleep 1
+    touch "$dir/src/main.rs"
+    local start end
+    start=$(date +%s.%N)
+    cargo build --quiet --manifest-path "$dir/Cargo.toml" "$@"
+    end=$(date +%s.%N)
+    awk "BEGIN { print $end - $start }"
+}
+
+# Size of the release binary's `.text` section, in bytes.
+text_size() {
+    size -A "$dir/target/release/dyn-write-size" | awk '$1 == ".text" { print $2 }'
+}
+
+printf '%-10s %12s %12s\n' "" "Default" "dyn-write"
+release=$(build_time --release)
+text=$(text_size)
+debug=$(build_time)
+release_dyn=$(build_time --release --features dyn-write)
+text_dyn=$(text_size)
+debug_dyn=$(build_time --features dyn-write)
+printf '%-10s %11.1fs %11.1fs\n' "Release" "$release" "$release_dyn"
+printf '%-10s %12s %12s\n' ".text" "$text" "$text_dyn"
+printf '%-10s %11.1fs %11.1fs\n' "Debug" "$debug" "$debug_dyn"
@@ -0,0 +1,285 @@
+//! Macros for [`serde-metaform`](https://docs.rs/serde-metaform).
+//!
+//! Use them through `serde_metaform` with its `derive` feature enabled, not
+//! from this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, Variant, ext::IdentExt, parse_macro_input};
+
+/// Implements `serde_metaform::IntoFormPairs` for a struct with named fields.
+///
+/// Fields are listed in declaration order under their names, honoring
+/// `#[serde(rename = "...")]` and `#[serde(skip)]`. Attributes that change the
+/// shape of the output, like `flatten` or `skip_serializing_if`, are rejected
+/// rather than ignored, so the pairs always match what `serde` would write.
+#[proc_macro_derive(IntoFormPairs, attributes(serde))]
+pub fn derive_into_form_pairs(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Fails to compile if the type would not serialize as an object.
+///
+/// The item is left as is. Only what can be told from its definition is
+/// checked: newtype structs and variants, `#[serde(transparent)]` and
+/// `#[serde(into = "...")]` hand serialization over to another type, and are
+/// accepted.
+#[proc_macro_attribute]
+pub fn assert_top_level_struct(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = TokenStream2::from(input.clone());
+    let error = if args.is_empty() {
+        let input = parse_macro_input!(input as DeriveInput);
+        check_top_level(&input).err()
+    } else {
+        Some(syn::Error::new(
+            TokenStream2::from(args).into_iter().next().unwrap().span(),
+            "assert_top_level_struct takes no arguments",
+        ))
+    };
+    let error = error.map(syn::Error::into_compile_error);
+    quote! { #error #item }.into()
+}
+
+/// How `serde` writes an enum, as set by its container attributes.
+#[derive(Default)]
+struct Container {
+    /// `transparent` or `into`: serialized as some other type.
+    forwarded: bool,
+    /// `tag = "..."`, alone or with `content`: every variant is an object.
+    tagged: bool,
+    untagged: bool,
+}
+
+fn check_top_level(input: &DeriveInput) -> syn::Result<()> {
+    let mut container = Container::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("transparent") || meta.path.is_ident("into") {
+                container.forwarded = true;
+            } else if meta.path.is_ident("tag") {
+                container.tagged = true;
+            } else if meta.path.is_ident("untagged") {
+                container.untagged = true;
+            }
+            skip_value(&meta)
+        })?;
+    }
+    if container.forwarded {
+        return Ok(());
+    }
+
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(_) => Ok(()),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Ok(()),
+            Fields::Unnamed(_) => Err(syn::Error::new(
+                input.ident.span(),
+                "a tuple struct serializes as a sequence, not an object",
+            )),
+            Fields::Unit => Err(syn::Error::new(
+                input.ident.span(),
+                "a unit struct has no fields to write as an object",
+            )),
+        },
+        Data::Enum(_) if container.tagged => Ok(()),
+        Data::Enum(data) => {
+            // Report every offending variant at once.
+            let mut errors = data
+                .variants
+                .iter()
+                .filter_map(|variant| check_variant(variant, container.untagged).err());
+            let Some(mut error) = errors.next() else {
+                return Ok(());
+            };
+            errors.for_each(|other| error.combine(other));
+            Err(error)
+        }
+        Data::Union(_) => Err(syn::Error::new(
+            input.ident.span(),
+            "unions can't be serialized",
+        )),
+    }
+}
+
+/// Returns an error if the variant would not serialize as an object.
+fn check_variant(variant: &Variant, untagged: bool) -> syn::Result<()> {
+    let mut skipped = false;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                skipped = true;
+            }
+            skip_value(&meta)
+        })?;
+    }
+    if skipped {
+        return Ok(());
+    }
+
+    let ident = &variant.ident;
+    let message = match &variant.fields {
+        Fields::Unit if untagged => "serializes as a unit, not an object",
+        Fields::Unit => "serializes as a bare string, not an object",
+        Fields::Unnamed(fields) if untagged && fields.unnamed.len() != 1 => {
+            "serializes as a sequence, not an object"
+        }
+        _ => return Ok(()),
+    };
+    Err(syn::Error::new(
+        ident.span(),
+        format!("variant `{ident}` {message}"),
+    ))
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    check_container_attrs(input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "IntoFormPairs needs a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "IntoFormPairs can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut pairs = Vec::new();
+    let mut bounds = Vec::new();
+    for field in fields {
+        let Some(name) = field_name(field)? else {
+            continue;
+        };
+        let ident = &field.ident;
+        let ty = &field.ty;
+        pairs.push(quote! {
+            (#name, &self.#ident as &dyn ::serde_metaform::__private::erased_serde::Serialize)
+        });
+        bounds.push(quote! { #ty: ::serde_metaform::__private::serde::Serialize });
+    }
+    let len = pairs.len();
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let predicates = where_clause.map(|clause| &clause.predicates);
+    Ok(quote! {
+        impl #impl_generics ::serde_metaform::IntoFormPairs for #ident #ty_generics
+        where
+            #predicates
+            #(#bounds,)*
+        {
+            fn into_form_pairs(
+                &self,
+            ) -> impl ::core::iter::Iterator<
+                Item = (
+                    &'static str,
+                    &dyn ::serde_metaform::__private::erased_serde::Serialize,
+                ),
+            > {
+                let pairs: [(
+                    &'static str,
+                    &dyn ::serde_metaform::__private::erased_serde::Serialize,
+                ); #len] = [#(#pairs),*];
+                pairs.into_iter()
+            }
+        }
+    })
+}
+
+/// Rejects container attributes that would rename or restructure the fields.
+fn check_container_attrs(input: &DeriveInput) -> syn::Result<()> {
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all")
+                || meta.path.is_ident("transparent")
+                || meta.path.is_ident("into")
+            {
+                return Err(meta.error("not supported by IntoFormPairs"));
+            }
+            skip_value(&meta)
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the key a field is written under, or `None` if it is skipped.
+fn field_name(field: &syn::Field) -> syn::Result<Option<LitStr>> {
+    let ident = field.ident.as_ref().expect("named field");
+    // Like serde, write `r#type` as `type`.
+    let mut name = LitStr::new(&ident.unraw().to_string(), ident.span());
+    let mut skip = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(syn::Token![=]) {
+                    name = meta.value()?.parse()?;
+                } else {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("serialize") {
+                            name = meta.value()?.parse()?;
+                            Ok(())
+                        } else {
+                            skip_value(&meta)
+                        }
+                    })?;
+                }
+                Ok(())
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                skip = true;
+                Ok(())
+            } else if [
+                "flatten",
+                "skip_serializing_if",
+                "serialize_with",
+                "with",
+                "getter",
+            ]
+            .iter()
+            .any(|unsupported| meta.path.is_ident(unsupported))
+            {
+                Err(meta.error("not supported by IntoFormPairs"))
+            } else {
+                skip_value(&meta)
+            }
+        })?;
+    }
+    Ok((!skip).then_some(name))
+}
+
+/// Consumes the value of an attribute this macro doesn't care about.
+fn skip_value(meta: &syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|meta| skip_value(&meta))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! [`actix-web`](actix_web) integration.
+//!
+//! This module is only available with the `actix` feature enabled. It provides
+//! [`MetaForm`], a responder that serializes any `serde::Serialize` value into
+//! a "Form + JSON" body.
+
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, ResponseError, body::BoxBody, http::StatusCode,
+};
+use serde::Serialize;
+
+use crate::{CONTENT_TYPE, error::Error};
+
+/// A "Form + JSON" response.
+///
+/// Wrapping a `serde::Serialize` value in `MetaForm` and returning it from a
+/// handler serializes the value with [`to_vec`](crate::to_vec) and sets the
+/// `Content-Type` header to [`CONTENT_TYPE`].
+///
+/// If serialization fails, the [`Error`] is turned into a
+/// `500 Internal Server Error` response carrying the error message.
+///
+/// # Example
+///
+/// ```rust
+/// use actix_web::{App, web};
+/// use serde::Serialize;
+/// use serde_metaform::actix::MetaForm;
+///
+/// #[derive(Serialize)]
+/// struct Reply {
+///     id: u64,
+///     ok: bool,
+/// }
+///
+/// async fn handler() -> MetaForm<Reply> {
+///     MetaForm(Reply { id: 1, ok: true })
+/// }
+///
+/// let app = App::new().route("/", web::post().to(handler));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetaForm<T>(pub T);
+
+impl<T> Responder for MetaForm<T>
+where
+    T: Serialize,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        match crate::to_vec(&self.0) {
+            Ok(body) => HttpResponse::Ok().content_type(CONTENT_TYPE).body(body),
+            Err(err) => err.error_response(),
+        }
+    }
+}
+
+/// Serialization errors are reported as `500 Internal Server Error`.
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{App, http::header, test, web};
+
+    #[derive(Serialize)]
+    struct Reply {
+        id: u64,
+        name: &'static str,
+    }
+
+    #[actix_web::test]
+    async fn test_respond_to() {
+        let app = test::init_service(App::new().route(
+            "/",
+            web::get().to(|| async {
+                MetaForm(Reply {
+                    id: 7,
+                    name: "John Doe",
+                })
+            }),
+        ))
+        .await;
+
+        let response = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            CONTENT_TYPE
+        );
+        assert_eq!(test::read_body(response).await, "id=7&name=John%20Doe");
+    }
+
+    #[actix_web::test]
+    async fn test_respond_to_error() {
+        // A top-level primitive is not a valid payload.
+        let app =
+            test::init_service(App::new().route("/", web::get().to(|| async { MetaForm(123) })))
+                .await;
+
+        let response = test::call_service(&app, test::TestRequest::get().to_request()).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            test::read_body(response).await,
+            "Top-level value must be a struct or map, but got i32"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! [`axum`](::axum) integration.
+//!
+//! This module is only available with the `axum` feature enabled. It provides
+//! [`MetaForm`], a response wrapper that serializes any `serde::Serialize`
+//! value into a "Form + JSON" body.
+
+use ::axum::{
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::CONTENT_TYPE;
+
+/// A "Form + JSON" response.
+///
+/// Wrapping a `serde::Serialize` value in `MetaForm` and returning it from a
+/// handler serializes the value with [`to_vec`](crate::to_vec) and sets the
+/// `Content-Type` header to [`CONTENT_TYPE`].
+///
+/// If serialization fails, a `500 Internal Server Error` response carrying the
+/// error message as plain text is returned instead.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{Router, routing::post};
+/// use serde::Serialize;
+/// use serde_metaform::axum::MetaForm;
+///
+/// #[derive(Serialize)]
+/// struct Reply {
+///     id: u64,
+///     ok: bool,
+/// }
+///
+/// async fn handler() -> MetaForm<Reply> {
+///     MetaForm(Reply { id: 1, ok: true })
+/// }
+///
+/// let app: Router = Router::new().route("/", post(handler));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetaForm<T>(pub T);
+
+impl<T> IntoResponse for MetaForm<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        match crate::to_vec(&self.0) {
+            Ok(body) => ([(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response(),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                err.to_string(),
+            )
+                .into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::axum::{Router, body::Body, http::Request, routing::get};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[derive(Serialize)]
+    struct Reply {
+        id: u64,
+        name: &'static str,
+    }
+
+    async fn call(app: Router) -> Response {
+        app.oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_into_response() {
+        let app = Router::new().route(
+            "/",
+            get(|| async {
+                MetaForm(Reply {
+                    id: 7,
+                    name: "John Doe",
+                })
+            }),
+        );
+
+        let response = call(app).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        assert_eq!(body_string(response).await, "id=7&name=John%20Doe");
+    }
+
+    #[tokio::test]
+    async fn test_into_response_error() {
+        // A top-level primitive is not a valid payload.
+        let app = Router::new().route("/", get(|| async { MetaForm(123) }));
+
+        let response = call(app).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body_string(response).await,
+            "Top-level value must be a struct or map, but got i32"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Batches of payloads in a single form body.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{Config, Serializer, error::Error, write::PercentEncoding};
+
+/// Serializes several payloads into one form body, with array-style keys.
+///
+/// Each field of the item at index `i` is written as `prefix[i][field]`, which
+/// is how batch endpoints commonly expect a list of objects. Values are written
+/// as JSON, exactly as [`to_string`](crate::to_string) would write them. Like
+/// every key, the brackets are percent-encoded, as `%5B` and `%5D`.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::BatchSerializer;
+///
+/// #[derive(Serialize)]
+/// struct Message { text: &'static str }
+///
+/// let mut out = String::new();
+/// let mut batch = BatchSerializer::new(&mut out, "messages");
+/// batch.serialize_item(&Message { text: "hi" }).unwrap();
+/// batch.serialize_item(&Message { text: "bye" }).unwrap();
+/// assert_eq!(out, "messages%5B0%5D%5Btext%5D=hi&messages%5B1%5D%5Btext%5D=bye");
+/// ```
+#[derive(Debug)]
+pub struct BatchSerializer<W> {
+    output: W,
+    /// The percent-encoded key prefix.
+    prefix: String,
+    index: usize,
+    is_first: bool,
+    /// Each item is serialized here first, then copied out with its keys
+    /// rewritten.
+    scratch: String,
+}
+
+impl<W: Write> BatchSerializer<W> {
+    /// Creates a batch serializer that writes to `writer`, with keys starting
+    /// with `key_prefix`.
+    pub fn new(writer: W, key_prefix: &str) -> Self {
+        let mut prefix = String::with_capacity(key_prefix.len());
+        // Writing to a `String` can't fail.
+        let _ = PercentEncoding::new(&mut prefix).write_str(key_prefix);
+        Self {
+            output: writer,
+            prefix,
+            index: 0,
+            is_first: true,
+            scratch: String::new(),
+        }
+    }
+
+    /// Serializes the next item of the batch.
+    ///
+    /// The item must serialize as a struct or map, like any payload. An item
+    /// with no fields writes nothing, but still takes up an index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails. See [`to_writer`](crate::to_writer)
+    /// for details. Nothing is written for the item in that case, and the next
+    /// item gets the same index.
+    pub fn serialize_item<T>(&mut self, item: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.scratch.clear();
+        item.serialize(Serializer::with_config(
+            &mut self.scratch,
+            Config::default(),
+        ))?;
+
+        // Keys and values are percent-encoded, so `&` and `=` only ever appear
+        // as separators.
+        for pair in self.scratch.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if !self.is_first {
+                self.output.write_str("&")?;
+            }
+            write!(
+                self.output,
+                "{}%5B{}%5D%5B{key}%5D={value}",
+                self.prefix, self.index
+            )?;
+            self.is_first = false;
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Unwraps the serializer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+/// Serializes `items` as a single form body with keys like `prefix[0][field]`.
+///
+/// See [`BatchSerializer`] for details.
+///
+/// # Errors
+///
+/// Returns an error if serializing an item fails. See [`to_writer`](crate::to_writer)
+/// for details.
+pub fn batch_serialize<T>(prefix: &str, items: &[T]) -> Result<String, Error>
+where
+    T: Serialize,
+{
+    let mut batch = BatchSerializer::new(String::with_capacity(128), prefix);
+    for item in items {
+        batch.serialize_item(item)?;
+    }
+    Ok(batch.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::prelude::rust_2024::*;
+
+    #[derive(Serialize)]
+    struct Message {
+        text: String,
+        tags: Vec<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reply_to: Option<u64>,
+    }
+
+    #[test]
+    fn test_batch_serialize() {
+        let messages = [
+            Message {
+                text: "hello world".to_owned(),
+                tags: vec!["a"],
+                reply_to: None,
+            },
+            Message {
+                text: "a=b&c".to_owned(),
+                tags: vec![],
+                reply_to: Some(7),
+            },
+        ];
+        assert_eq!(
+            batch_serialize("messages", &messages).unwrap(),
+            "messages%5B0%5D%5Btext%5D=hello%20world\
+             &messages%5B0%5D%5Btags%5D=%5B%22a%22%5D\
+             &messages%5B1%5D%5Btext%5D=a%3Db%26c\
+             &messages%5B1%5D%5Btags%5D=%5B%5D\
+             &messages%5B1%5D%5Breply_to%5D=7"
+        );
+        assert_eq!(batch_serialize::<Message>("messages", &[]).unwrap(), "");
+    }
+
+    #[test]
+    fn test_prefix_and_keys_are_encoded() {
+        let items = [BTreeMap::from([("a b", 1)])];
+        assert_eq!(
+            batch_serialize("my list", &items).unwrap(),
+            "my%20list%5B0%5D%5Ba%20b%5D=1"
+        );
+    }
+
+    #[test]
+    fn test_empty_and_failed_items() {
+        let mut batch = BatchSerializer::new(String::new(), "items");
+        batch.serialize_item(&BTreeMap::<&str, u8>::new()).unwrap();
+        assert!(batch.serialize_item(&1).is_err());
+        batch.serialize_item(&BTreeMap::from([("id", 2)])).unwrap();
+        // The empty item took index 0, the failed one took none.
+        assert_eq!(batch.into_inner(), "items%5B1%5D%5Bid%5D=2");
+    }
+}
@@ -0,0 +1,132 @@
+//! Lossless [`BigDecimal`] serialization.
+//!
+//! This module is only available with the `bigdecimal` feature enabled.
+//! `BigDecimal` serializes itself as a string, so `1.00000000000000001` would
+//! be written as the JSON string `"1.00000000000000001"`, and converting it to
+//! `f64` first loses precision. [`serialize`] writes it as a JSON number literal
+//! instead, digit for digit and never in scientific notation.
+//!
+//! ```rust
+//! # use serde::Serialize;
+//! use bigdecimal::BigDecimal;
+//!
+//! #[derive(Serialize)]
+//! struct Payment {
+//!     #[serde(serialize_with = "serde_metaform::bigdecimal::serialize")]
+//!     amount: BigDecimal,
+//! }
+//!
+//! let payment = Payment { amount: "1.00000000000000001".parse().unwrap() };
+//! assert_eq!(
+//!     serde_metaform::to_string(&payment).unwrap(),
+//!     "amount=1.00000000000000001"
+//! );
+//! ```
+
+use std::fmt;
+
+use bigdecimal::BigDecimal;
+use serde::{Serialize, Serializer};
+
+use crate::raw::RAW_TOKEN;
+
+/// Serializes a [`BigDecimal`] as a JSON number literal.
+///
+/// Use it with `#[serde(serialize_with = "serde_metaform::bigdecimal::serialize")]`.
+/// Serializers other than this crate's write the number as a string, just like
+/// `BigDecimal`'s own `Serialize` implementation does.
+pub fn serialize<S>(value: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_newtype_struct(RAW_TOKEN, &PlainString(value))
+}
+
+/// Serializes a `BigDecimal` as its plain (non-scientific) string.
+struct PlainString<'a>(&'a BigDecimal);
+
+impl fmt::Display for PlainString<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_plain_string(f)
+    }
+}
+
+impl Serialize for PlainString<'_> {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[derive(Serialize)]
+    struct Payment {
+        #[serde(serialize_with = "serialize")]
+        amount: BigDecimal,
+    }
+
+    #[derive(Serialize)]
+    struct Invoice {
+        total: Payment,
+        lines: Vec<Payment>,
+    }
+
+    fn payment(amount: &str) -> Payment {
+        Payment {
+            amount: BigDecimal::from_str(amount).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_no_precision_loss() {
+        assert_eq!(
+            crate::to_string(&payment("1.00000000000000001")).unwrap(),
+            "amount=1.00000000000000001"
+        );
+        assert_eq!(crate::to_string(&payment("-0.5")).unwrap(), "amount=-0.5");
+    }
+
+    #[test]
+    fn test_no_scientific_notation() {
+        assert_eq!(
+            crate::to_string(&payment("1e30")).unwrap(),
+            "amount=1000000000000000000000000000000"
+        );
+        assert_eq!(
+            crate::to_string(&payment("1.5e-20")).unwrap(),
+            "amount=0.000000000000000000015"
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        let invoice = Invoice {
+            total: payment("12345678901234567890.123456789"),
+            lines: vec![payment("0.1"), payment("2")],
+        };
+        // total: {"amount":12345678901234567890.123456789}
+        // lines: [{"amount":0.1},{"amount":2}]
+        assert_eq!(
+            crate::to_string(&invoice).unwrap(),
+            "total=%7B%22amount%22%3A12345678901234567890.123456789%7D\
+             &lines=%5B%7B%22amount%22%3A0.1%7D%2C%7B%22amount%22%3A2%7D%5D"
+        );
+    }
+
+    #[test]
+    fn test_other_serializers() {
+        // Without this crate's serializer, the number is written as a string.
+        assert_eq!(
+            serde_json::to_string(&payment("1.00000000000000001")).unwrap(),
+            r#"{"amount":"1.00000000000000001"}"#
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! A reusable, shareable encoder configuration.
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{Config, Serializer, error::Error};
+
+/// Encoding configuration that can be shared and reused.
+///
+/// A [`Serializer`] is configured once and consumed by a single value.
+/// `FormEncoder` holds the same options, but is `Clone`, `Send` and `Sync`, so
+/// one instance can be kept in server state or middleware and used from any
+/// thread to encode any number of values.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::FormEncoder;
+///
+/// #[derive(Serialize)]
+/// struct Flags { active: bool, tags: Vec<&'static str> }
+///
+/// let encoder = FormEncoder::new().bool_as_int(true);
+/// let encoded = encoder.encode(&Flags { active: true, tags: vec!["a"] }).unwrap();
+/// assert_eq!(encoded, "active=1&tags=%5B%22a%22%5D");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FormEncoder {
+    config: Config,
+}
+
+impl Default for FormEncoder {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            config: Config {
+                transactional: true,
+                ..Config::default()
+            },
+        }
+    }
+}
+
+impl FormEncoder {
+    /// Creates an encoder with the default options, matching [`to_string`](crate::to_string),
+    /// except that pairs are written [transactionally](Self::transactional).
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [`Serializer::force_json_string_values`].
+    #[inline]
+    pub fn force_json_string_values(mut self, force: bool) -> Self {
+        self.config.force_json_string_values = force;
+        self
+    }
+
+    /// See [`Serializer::bool_as_int`].
+    #[inline]
+    pub fn bool_as_int(mut self, bool_as_int: bool) -> Self {
+        self.config.bool_as_int = bool_as_int;
+        self
+    }
+
+    /// See [`Serializer::unicode_escape_non_ascii`].
+    #[inline]
+    pub fn unicode_escape_non_ascii(mut self, escape: bool) -> Self {
+        self.config.unicode_escape_non_ascii = escape;
+        self
+    }
+
+    /// See [`Serializer::kv_separator`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `separator` contains `&` or isn't ASCII.
+    #[inline]
+    pub fn kv_separator(mut self, separator: &'static str) -> Self {
+        self.config.set_kv_separator(separator);
+        self
+    }
+
+    /// See [`Serializer::flat_strict`].
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn flat_strict(mut self, strict: bool) -> Self {
+        self.config.flat_strict = strict;
+        self
+    }
+
+    /// See [`Serializer::allow_empty_top_level`].
+    #[inline]
+    pub fn allow_empty_top_level(mut self, allow: bool) -> Self {
+        self.config.allow_empty_top_level = allow;
+        self
+    }
+
+    /// See [`Serializer::skip_default_values`].
+    #[inline]
+    pub fn skip_default_values(mut self, skip: bool) -> Self {
+        self.config.skip_default_values = skip;
+        self
+    }
+
+    /// See [`Serializer::transactional`].
+    ///
+    /// Enabled by default, so a failed [`encode_to_writer`](Self::encode_to_writer)
+    /// never leaves a partial pair in the writer.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn transactional(mut self, transactional: bool) -> Self {
+        self.config.transactional = transactional;
+        self
+    }
+
+    /// Creates a [`Serializer`] with this encoder's options.
+    #[inline]
+    pub fn serializer<W: Write>(&self, writer: W) -> Serializer<W> {
+        Serializer::with_config(writer, self.config)
+    }
+
+    /// Serializes `value` into `writer` with this encoder's options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails. See [`to_writer`](crate::to_writer)
+    /// for details.
+    #[inline]
+    pub fn encode_to_writer<W, T>(&self, writer: W, value: &T) -> Result<(), Error>
+    where
+        W: Write,
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self.serializer(writer))
+    }
+
+    /// Serializes `value` as a `String` with this encoder's options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails. See [`to_writer`](crate::to_writer)
+    /// for details.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn encode<T>(&self, value: &T) -> Result<String, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut writer = String::with_capacity(128);
+        // A failed encode returns no output, so there is nothing to protect.
+        let config = Config {
+            transactional: false,
+            ..self.config
+        };
+        value.serialize(Serializer::with_config(&mut writer, config))?;
+        Ok(writer)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::prelude::rust_2024::*;
+
+    #[test]
+    fn test_send_sync_clone() {
+        fn assert_shareable<T: Send + Sync + Clone + 'static>() {}
+        assert_shareable::<FormEncoder>();
+    }
+
+    #[test]
+    fn test_encode() {
+        let value = BTreeMap::from([("name", "John Doe")]);
+        assert_eq!(
+            FormEncoder::new().encode(&value).unwrap(),
+            crate::to_string(&value).unwrap()
+        );
+        assert_eq!(
+            FormEncoder::new()
+                .force_json_string_values(true)
+                .encode(&BTreeMap::from([("n", 1)]))
+                .unwrap(),
+            "n=%221%22"
+        );
+        assert_eq!(
+            FormEncoder::new().flat_strict(true).encode(&value).unwrap(),
+            "name=John+Doe"
+        );
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let encoder = std::sync::Arc::new(FormEncoder::new().bool_as_int(true));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let encoder = encoder.clone();
+                std::thread::spawn(move || encoder.encode(&BTreeMap::from([("on", i % 2 == 0)])))
+            })
+            .collect();
+        let encoded: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap().unwrap())
+            .collect();
+        assert_eq!(encoded, ["on=1", "on=0", "on=1", "on=0"]);
+    }
+}
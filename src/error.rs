@@ -1,4 +1,6 @@
-use std::fmt;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::ToString};
+use core::fmt;
 
 #[derive(Debug)]
 pub struct Error {
@@ -14,12 +16,104 @@ impl fmt::Display for Error {
             }
             ErrorInner::KeyMustBeAString(t) => write!(f, "Map key must be a string, but got {t}"),
             ErrorInner::FloatKeyMustBeFinite => write!(f, "Map key must be finite"),
-            ErrorInner::Fmt => write!(f, "Error writing to the underlying write"),
+            ErrorInner::NestedValueInFlatMode(key) => {
+                write!(f, "Value of `{key}` must be a scalar in flat mode")
+            }
+            ErrorInner::BufferTooSmall { needed, capacity } => write!(
+                f,
+                "Buffer too small: {needed} bytes needed, but the buffer holds {capacity}"
+            ),
+            ErrorInner::Poisoned => {
+                write!(f, "Serializer used again after a pair failed to serialize")
+            }
+            ErrorInner::Fmt(_) => write!(f, "Error writing to the underlying write"),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the category of this error.
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
+        match self.inner {
+            ErrorInner::Message(_) => ErrorKind::Message,
+            ErrorInner::NotAnObject(_) => ErrorKind::NotAnObject,
+            ErrorInner::KeyMustBeAString(_) => ErrorKind::KeyMustBeAString,
+            ErrorInner::FloatKeyMustBeFinite => ErrorKind::FloatKeyMustBeFinite,
+            ErrorInner::NestedValueInFlatMode(_) => ErrorKind::NestedValueInFlatMode,
+            ErrorInner::BufferTooSmall { .. } => ErrorKind::BufferTooSmall,
+            ErrorInner::Poisoned => ErrorKind::Poisoned,
+            ErrorInner::Fmt(_) => ErrorKind::Fmt,
+        }
+    }
+
+    /// Returns `true` if this error is of the given kind.
+    ///
+    /// ```
+    /// use serde_metaform::error::ErrorKind;
+    ///
+    /// let err = serde_metaform::to_string(&42).unwrap_err();
+    /// assert!(err.matches(ErrorKind::NotAnObject));
+    /// assert_eq!(err.unexpected_type(), Some("i32"));
+    /// ```
+    #[inline]
+    pub fn matches(&self, kind: ErrorKind) -> bool {
+        self.kind() == kind
+    }
+
+    /// Returns the name of the type that was rejected, for
+    /// [`ErrorKind::NotAnObject`] and [`ErrorKind::KeyMustBeAString`] errors.
+    #[inline]
+    pub fn unexpected_type(&self) -> Option<&'static str> {
+        match self.inner {
+            ErrorInner::NotAnObject(t) | ErrorInner::KeyMustBeAString(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns the key whose value was rejected, for
+    /// [`ErrorKind::NestedValueInFlatMode`] errors.
+    ///
+    /// Without the `alloc` feature, long keys are truncated.
+    #[inline]
+    pub fn key(&self) -> Option<&str> {
+        match &self.inner {
+            ErrorInner::NestedValueInFlatMode(key) => Some(text_str(key)),
+            _ => None,
+        }
+    }
+
+    /// Returns the text of a custom error, for [`ErrorKind::Message`] errors.
+    ///
+    /// Without the `alloc` feature, long messages are truncated.
+    #[inline]
+    pub fn message(&self) -> Option<&str> {
+        match &self.inner {
+            ErrorInner::Message(msg) => Some(text_str(msg)),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes the payload needed if this error was
+    /// returned by [`to_slice`](crate::to_slice) because the buffer was too
+    /// small.
+    #[inline]
+    pub fn needed_len(&self) -> Option<usize> {
+        match self.inner {
+            ErrorInner::BufferTooSmall { needed, .. } => Some(needed),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match &self.inner {
+            ErrorInner::Fmt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl serde::ser::Error for Error {
     fn custom<T>(msg: T) -> Self
@@ -27,41 +121,209 @@ impl serde::ser::Error for Error {
         T: fmt::Display,
     {
         Error {
-            inner: ErrorInner::Message(msg.to_string().into()),
+            inner: ErrorInner::Message(text(&msg)),
         }
     }
 }
 
-impl From<std::fmt::Error> for Error {
-    fn from(_: std::fmt::Error) -> Self {
+impl From<core::fmt::Error> for Error {
+    fn from(err: core::fmt::Error) -> Self {
         Self {
-            inner: ErrorInner::Fmt,
+            inner: ErrorInner::Fmt(err),
         }
     }
 }
 
+// The constructors below are only called on failure paths. Kept out of line,
+// they don't bloat the serializers' hot loops.
+
+#[cold]
+#[inline(never)]
 pub(crate) const fn top_level_must_be_object(got: &'static str) -> Error {
     Error {
         inner: ErrorInner::NotAnObject(got),
     }
 }
 
+#[cold]
+#[inline(never)]
 pub(crate) const fn key_must_be_string(got: &'static str) -> Error {
     Error {
         inner: ErrorInner::KeyMustBeAString(got),
     }
 }
 
+#[cold]
+#[inline(never)]
 pub(crate) const fn float_key_must_be_finite() -> Error {
     Error {
         inner: ErrorInner::FloatKeyMustBeFinite,
     }
 }
 
+#[cold]
+#[inline(never)]
+pub(crate) fn nested_value_in_flat_mode(key: &str) -> Error {
+    Error {
+        inner: ErrorInner::NestedValueInFlatMode(text(key)),
+    }
+}
+
+#[cold]
+#[inline(never)]
+pub(crate) const fn buffer_too_small(needed: usize, capacity: usize) -> Error {
+    Error {
+        inner: ErrorInner::BufferTooSmall { needed, capacity },
+    }
+}
+
+#[cold]
+#[inline(never)]
+pub(crate) const fn poisoned() -> Error {
+    Error {
+        inner: ErrorInner::Poisoned,
+    }
+}
+
+/// Text carried by an error: a custom message, or a key.
+#[cfg(feature = "alloc")]
+pub(crate) type Text = Box<str>;
+/// Text carried by an error: a custom message, or a key.
+///
+/// Without `alloc`, it is truncated to fit inline.
+#[cfg(not(feature = "alloc"))]
+pub(crate) type Text = InlineText;
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn text_str(text: &Text) -> &str {
+    text
+}
+
+#[cfg(not(feature = "alloc"))]
+#[inline]
+fn text_str(text: &Text) -> &str {
+    text.as_str()
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn text<T: ?Sized + fmt::Display>(value: &T) -> Text {
+    value.to_string().into()
+}
+
+#[cfg(not(feature = "alloc"))]
+#[inline]
+fn text<T: ?Sized + fmt::Display>(value: &T) -> Text {
+    InlineText::new(value)
+}
+
+/// The most bytes of text an [`InlineText`] holds.
+#[cfg(not(feature = "alloc"))]
+const INLINE_TEXT_CAPACITY: usize = 64;
+
+/// Error text stored in a fixed-size buffer, for builds without `alloc`.
+///
+/// Text that doesn't fit is cut at a character boundary, and displayed with
+/// a trailing `...`.
+#[cfg(not(feature = "alloc"))]
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct InlineText {
+    buf: [u8; INLINE_TEXT_CAPACITY],
+    len: u8,
+    truncated: bool,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl InlineText {
+    fn new<T: ?Sized + fmt::Display>(value: &T) -> Self {
+        use fmt::Write as _;
+
+        let mut text = Self {
+            buf: [0; INLINE_TEXT_CAPACITY],
+            len: 0,
+            truncated: false,
+        };
+        // Writing never fails: overflow only truncates.
+        let _ = write!(text, "{value}");
+        text
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole characters are ever copied in.
+        core::str::from_utf8(&self.buf[..usize::from(self.len)]).unwrap_or_default()
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl fmt::Write for InlineText {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        let len = usize::from(self.len);
+        let mut end = s.len().min(INLINE_TEXT_CAPACITY - len);
+        if end < s.len() {
+            self.truncated = true;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+        }
+        self.buf[len..len + end].copy_from_slice(&s.as_bytes()[..end]);
+        self.len += end as u8;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl fmt::Display for InlineText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())?;
+        if self.truncated {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+impl fmt::Debug for InlineText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)?;
+        if self.truncated {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+
+/// The category of an [`Error`], as returned by [`Error::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A custom error raised by a `Serialize` implementation.
+    Message,
+    /// The top-level value does not serialize to a JSON object.
+    NotAnObject,
+    /// A map key does not serialize to a string.
+    KeyMustBeAString,
+    /// A floating-point map key is NaN or infinite.
+    FloatKeyMustBeFinite,
+    /// A value in flat mode was not a scalar.
+    NestedValueInFlatMode,
+    /// The output did not fit in the caller's buffer.
+    BufferTooSmall,
+    /// An earlier pair failed, and the serializer was not
+    /// [`reset`](crate::Serializer::reset).
+    Poisoned,
+    /// The underlying writer failed.
+    Fmt,
+}
+
 #[derive(Debug, PartialEq)]
 pub(crate) enum ErrorInner {
     /// A custom error message.
-    Message(Box<str>),
+    Message(Text),
     /// The top-level value was not a type that'll end-up being an object
     /// if serialized by serde_json.
     NotAnObject(&'static str),
@@ -69,8 +331,14 @@ pub(crate) enum ErrorInner {
     KeyMustBeAString(&'static str),
     /// Object key is a non-finite float value.
     FloatKeyMustBeFinite,
+    /// A value in flat mode was not a scalar (string, number, bool or `None`).
+    NestedValueInFlatMode(Text),
+    /// The output did not fit in the caller's buffer.
+    BufferTooSmall { needed: usize, capacity: usize },
+    /// A key or value was written after an earlier one failed.
+    Poisoned,
     /// An I/O error occurred in the writer.
-    Fmt,
+    Fmt(fmt::Error),
 }
 
 #[macro_export]
@@ -113,7 +381,10 @@ macro_rules! forward_unit {
         $crate::serialize_normal!(@$is_err $f, [bool]);
     };
     (@$is_err:ident $f:expr, integers) => {
-        $crate::serialize_normal!(@$is_err $f, [i8 i16 i32 i64 u8 u16 u32 u64 f32 f64]);
+        $crate::serialize_normal!(@$is_err $f, [i8 i16 i32 i64 i128 u8 u16 u32 u64 u128]);
+    };
+    (@$is_err:ident $f:expr, floats) => {
+        $crate::serialize_normal!(@$is_err $f, [f32 f64]);
     };
     (@$is_err:ident $f:expr, char) => {
         $crate::serialize_normal!(@$is_err $f, [char]);
@@ -130,23 +401,6 @@ macro_rules! forward_unit {
                 {$f}
             }
         }
-
-        #[inline]
-        #[allow(unused_variables)]
-        fn serialize_unit_variant(
-            self,
-            name: &'static str,
-            variant_index: u32,
-            variant: &'static str,
-        ) -> Result<Self::Ok, Self::Error> {
-            $crate::cfg_error! {
-                $is_err
-
-                {Err($f("UnitVariant"))}
-
-                {$f}
-            }
-       }
     };
     (@$is_err:ident $f:expr, bytes) => {
         #[inline]
@@ -327,3 +581,72 @@ macro_rules! defer_float_to_write_or_not_finite {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::ser::Error as _;
+    use std::prelude::rust_2024::*;
+
+    #[test]
+    fn test_kind_and_accessors() {
+        let err = Error::custom("bad value");
+        assert!(err.matches(ErrorKind::Message));
+        assert_eq!(err.message(), Some("bad value"));
+        assert_eq!(err.key(), None);
+        assert_eq!(err.unexpected_type(), None);
+
+        let err = nested_value_in_flat_mode("tags");
+        assert_eq!(err.kind(), ErrorKind::NestedValueInFlatMode);
+        assert_eq!(err.key(), Some("tags"));
+        assert_eq!(err.message(), None);
+
+        let err = key_must_be_string("Seq");
+        assert!(err.matches(ErrorKind::KeyMustBeAString));
+        assert!(!err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("Seq"));
+
+        assert!(float_key_must_be_finite().matches(ErrorKind::FloatKeyMustBeFinite));
+        assert!(buffer_too_small(2, 1).matches(ErrorKind::BufferTooSmall));
+        assert!(Error::from(fmt::Error).matches(ErrorKind::Fmt));
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn test_custom_message_fits() {
+        let err = Error::custom("Map key must be a string");
+        assert!(err.matches(ErrorKind::Message));
+        assert_eq!(err.message(), Some("Map key must be a string"));
+        assert_eq!(err.to_string(), "Map key must be a string");
+
+        let exact = "x".repeat(INLINE_TEXT_CAPACITY);
+        assert_eq!(Error::custom(&exact).to_string(), exact);
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn test_custom_message_truncated() {
+        let long = "a".repeat(INLINE_TEXT_CAPACITY + 1);
+        let err = Error::custom(&long);
+        assert!(err.matches(ErrorKind::Message));
+        assert_eq!(err.message(), Some(&long[..INLINE_TEXT_CAPACITY]));
+        assert_eq!(
+            err.to_string(),
+            format!("{}...", &long[..INLINE_TEXT_CAPACITY])
+        );
+
+        // Written in pieces, nothing is appended after the first cut.
+        let err = Error::custom(format_args!("{long} and more"));
+        assert_eq!(err.message(), Some(&long[..INLINE_TEXT_CAPACITY]));
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn test_custom_message_truncated_on_char_boundary() {
+        // 63 bytes, then a 2-byte character that doesn't fit.
+        let long = format!("{}é", "a".repeat(INLINE_TEXT_CAPACITY - 1));
+        let err = Error::custom(&long);
+        assert_eq!(err.message(), Some(&long[..INLINE_TEXT_CAPACITY - 1]));
+        assert!(err.to_string().ends_with("a..."));
+    }
+}
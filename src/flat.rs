@@ -0,0 +1,270 @@
+//! Value serialization for [`Serializer::flat_strict`].
+//!
+//! In flat mode, the top-level values are written the way `serde_urlencoded`
+//! writes them instead of as JSON: strings are not quoted, spaces become `+`,
+//! `None` omits the pair entirely, and anything that isn't a scalar is an error.
+
+use core::fmt::Write;
+
+use serde::{Serialize, ser};
+
+#[cfg(feature = "tracing")]
+use crate::write::Counting;
+use crate::{
+    Config, Serializer,
+    error::{Error, nested_value_in_flat_mode},
+    write::write_urlencoded,
+};
+
+/// Serializes a single top-level value in flat mode.
+///
+/// The key has already been rendered into `ser.pending`. The pair, including the
+/// `&` separator, is only written once a value is known to be present.
+pub(crate) struct FlatValueSerializer<'a, W> {
+    pub(crate) ser: &'a mut Serializer<W>,
+    /// The key in `ser.pending` is already encoded, and is written as is.
+    pub(crate) raw_key: bool,
+}
+
+impl<W: Write> FlatValueSerializer<'_, W> {
+    #[inline]
+    fn write_pair(self, value: &str) -> Result<(), Error> {
+        let ser = self.ser;
+        #[cfg(feature = "tracing")]
+        if ser.trace.on {
+            let mut output = Counting::new(&mut ser.output);
+            write_flat_key(
+                &mut output,
+                ser.pairs,
+                &ser.pending,
+                self.raw_key,
+                ser.config,
+            )?;
+            ser.trace.key_len = output.count();
+            write_urlencoded(&mut output, value)?;
+            let value_len = output.count() - ser.trace.key_len;
+            ser.pairs += 1;
+            ser.trace.pair(value_len);
+            return Ok(());
+        }
+        write_flat_key(
+            &mut ser.output,
+            ser.pairs,
+            &ser.pending,
+            self.raw_key,
+            ser.config,
+        )?;
+        write_urlencoded(&mut ser.output, value)?;
+        ser.pairs += 1;
+        Ok(())
+    }
+
+    #[inline]
+    fn nested(self) -> Error {
+        nested_value_in_flat_mode(&self.ser.pending)
+    }
+}
+
+/// Writes `key` and the key-value separator, preceded by `&` unless it is the
+/// first pair.
+#[inline]
+fn write_flat_key<W: Write>(
+    output: &mut W,
+    pairs: usize,
+    key: &str,
+    raw_key: bool,
+    config: Config,
+) -> core::fmt::Result {
+    if pairs > 0 {
+        output.write_str("&")?;
+    }
+    if raw_key {
+        output.write_str(key)?;
+    } else {
+        write_urlencoded(output, key)?;
+    }
+    output.write_str(config.kv_separator)
+}
+
+macro_rules! flat_integer {
+    ($($ty:ident)*) => {
+        paste::paste! {
+            $(
+                #[inline]
+                fn [<serialize_ $ty>](self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                    self.write_pair(itoa::Buffer::new().format(v))
+                }
+            )*
+        }
+    }
+}
+
+macro_rules! flat_float {
+    ($($ty:ident)*) => {
+        paste::paste! {
+            $(
+                #[inline]
+                fn [<serialize_ $ty>](self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                    // Like `serde_urlencoded`, non-finite floats are written
+                    // as `NaN`, `inf` and `-inf`.
+                    self.write_pair(ryu::Buffer::new().format(v))
+                }
+            )*
+        }
+    }
+}
+
+impl<W: Write> ser::Serializer for FlatValueSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<(), Error>;
+    type SerializeTuple = ser::Impossible<(), Error>;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = ser::Impossible<(), Error>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    flat_integer! { i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 }
+    flat_float! { f32 f64 }
+
+    #[inline]
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.write_pair(if v { "true" } else { "false" })
+    }
+
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        let mut buf = [0; 4];
+        self.write_pair(v.encode_utf8(&mut buf))
+    }
+
+    #[inline]
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.write_pair(v)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        match core::str::from_utf8(v) {
+            Ok(v) => self.write_pair(v),
+            Err(_) => Err(self.nested()),
+        }
+    }
+
+    /// Omits the pair entirely.
+    #[inline]
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.write_pair(name)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.write_pair(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(self.nested())
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(self.nested())
+    }
+}
@@ -0,0 +1,118 @@
+//! Fixed-capacity [`heapless`](::heapless) output.
+//!
+//! This module is only available with the `heapless` feature enabled, which
+//! doesn't need `std`. [`to_heapless`] serializes into a `heapless::String<N>`
+//! and reports how large `N` needed to be when the payload doesn't fit.
+//!
+//! [`to_writer`] also accepts a `&mut heapless::String<N>`, but an overflow
+//! then surfaces as a bare formatting error.
+
+use core::fmt::Write;
+
+use serde::Serialize;
+
+use crate::{
+    error::{Error, buffer_too_small},
+    to_writer,
+};
+
+/// Serializes the given data structure into a `heapless::String<N>`.
+///
+/// The output is the same as `to_string`'s. Nothing is allocated on the heap
+/// unless serialization fails with a custom error message and the `alloc`
+/// feature is enabled.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::heapless::to_heapless;
+///
+/// #[derive(Serialize)]
+/// struct Point { x: i32, y: i32 }
+///
+/// let body = to_heapless::<16, _>(&Point { x: 1, y: 2 }).unwrap();
+/// assert_eq!(body, "x=1&y=2");
+///
+/// let err = to_heapless::<4, _>(&Point { x: 1, y: 2 }).unwrap_err();
+/// assert_eq!(err.needed_len(), Some(7));
+/// ```
+///
+/// # Errors
+///
+/// If the payload doesn't fit, returns an error whose
+/// [`needed_len`](Error::needed_len) is the size of the whole payload.
+/// Otherwise, see [`to_writer`] for details.
+#[inline]
+pub fn to_heapless<const N: usize, T>(value: &T) -> Result<heapless::String<N>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = HeaplessWrite {
+        out: heapless::String::new(),
+        len: 0,
+    };
+    to_writer(&mut writer, value)?;
+    if writer.len > N {
+        return Err(buffer_too_small(writer.len, N));
+    }
+    Ok(writer.out)
+}
+
+/// Fills a `heapless::String`, then only counts the output that didn't fit.
+struct HeaplessWrite<const N: usize> {
+    out: heapless::String<N>,
+    len: usize,
+}
+
+impl<const N: usize> Write for HeaplessWrite<N> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // A failed push leaves `out` unchanged, and short of `len` from then on.
+        if self.len == self.out.len() {
+            let _ = self.out.push_str(s);
+        }
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    #[derive(Serialize)]
+    struct Reading {
+        id: u32,
+        tags: [&'static str; 2],
+    }
+
+    const READING: Reading = Reading {
+        id: 7,
+        tags: ["a", "b"],
+    };
+    /// `id=7&tags=%5B%22a%22%2C%22b%22%5D`
+    const READING_LEN: usize = 33;
+
+    #[test]
+    fn test_exact_fit() {
+        let out = to_heapless::<READING_LEN, _>(&READING).unwrap();
+        assert_eq!(out, "id=7&tags=%5B%22a%22%2C%22b%22%5D");
+        assert_eq!(out.len(), READING_LEN);
+    }
+
+    #[test]
+    fn test_overflow_by_one() {
+        let err = to_heapless::<{ READING_LEN - 1 }, _>(&READING).unwrap_err();
+        assert!(err.matches(ErrorKind::BufferTooSmall));
+        assert_eq!(err.needed_len(), Some(READING_LEN));
+    }
+
+    #[test]
+    fn test_overflow_then_small_writes() {
+        // Later writes that would fit on their own are not appended.
+        let err = to_heapless::<6, _>(&READING).unwrap_err();
+        assert_eq!(err.needed_len(), Some(READING_LEN));
+    }
+}
@@ -27,30 +27,58 @@
 //! the parent module's hybrid format possible. If you know a way to solve this
 //! composition problem without this module, we welcome a PR! 🙏
 
-use std::fmt::Write;
+use core::fmt::Write;
 
 use serde::{Serialize, ser};
 
 use crate::{
+    Config,
     error::{Error, float_key_must_be_finite, key_must_be_string},
     error_unsupported,
-    write::WWrite,
+    raw::RAW_TOKEN,
+    write::{UnicodeEscape, WWrite, WWritePrimitives},
 };
 
 pub struct SeqSerializer<W> {
     output: W,
     is_first: bool,
+    config: Config,
+    /// The depth of the elements: the number of arrays and objects they are
+    /// nested in, this one included.
+    depth: usize,
 }
 
 impl<W: WWrite> SeqSerializer<W> {
     #[inline]
-    pub(crate) fn new(mut output: W, _len: Option<usize>) -> Result<Self, Error> {
+    pub(crate) fn new(
+        mut output: W,
+        _len: Option<usize>,
+        config: Config,
+        depth: usize,
+    ) -> Result<Self, Error> {
         output.write_left_sq_bracket()?;
+        output.nested(depth);
         Ok(SeqSerializer {
             output,
             is_first: true,
+            config,
+            depth,
         })
     }
+
+    /// Closes the sequence and returns the underlying writer.
+    #[inline]
+    pub(crate) fn finish(mut self) -> Result<W, Error> {
+        self.output.write_right_sq_bracket()?;
+        Ok(self.output)
+    }
+
+    /// Returns a reference to the underlying writer.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn output(&self) -> &W {
+        &self.output
+    }
 }
 
 impl<W: WWrite> ser::SerializeSeq for SeqSerializer<W> {
@@ -69,14 +97,17 @@ impl<W: WWrite> ser::SerializeSeq for SeqSerializer<W> {
         value.serialize(JsonSerializer {
             output: self.output.as_mut(),
             is_top_level_value: false,
+            config: self.config,
+            depth: self.depth,
         })?;
         self.is_first = false;
         Ok(())
     }
 
     #[inline]
-    fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        Ok(self.output.write_right_sq_bracket()?)
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()?;
+        Ok(())
     }
 }
 
@@ -130,16 +161,22 @@ pub struct TupleVariantSerializer<W> {
 
 impl<W: WWrite> TupleVariantSerializer<W> {
     #[inline]
-    pub fn new(mut output: W, variant: &'static str, len: usize) -> Result<Self, Error> {
+    pub fn new(
+        mut output: W,
+        variant: &'static str,
+        len: usize,
+        config: Config,
+        depth: usize,
+    ) -> Result<Self, Error> {
         // Write the outer map structure `{"variant":`
         {
             use ser::SerializeMap as _;
 
-            let mut map = MapSerializer::new(output.as_mut(), Some(1))?;
+            let mut map = MapSerializer::new(output.as_mut(), Some(1), config, depth)?;
             map.serialize_key(variant)?;
         }
         // Now, start the inner sequence.
-        let seq = SeqSerializer::new(output, Some(len))?;
+        let seq = SeqSerializer::new(output, Some(len), config, depth + 1)?;
         Ok(Self { inner: seq })
     }
 }
@@ -176,16 +213,22 @@ pub struct StructVariantSerializer<W: WWrite> {
 
 impl<W: WWrite> StructVariantSerializer<W> {
     #[inline]
-    pub fn new(mut output: W, variant: &'static str, len: usize) -> Result<Self, Error> {
+    pub fn new(
+        mut output: W,
+        variant: &'static str,
+        len: usize,
+        config: Config,
+        depth: usize,
+    ) -> Result<Self, Error> {
         // Write the outer map structure `{"variant":`
         {
             use ser::SerializeMap as _;
 
-            let mut map = MapSerializer::new(output.as_mut(), Some(1))?;
+            let mut map = MapSerializer::new(output.as_mut(), Some(1), config, depth)?;
             map.serialize_key(variant)?;
         }
         // Now, start the inner struct map.
-        let map = StructSerializer::new(output, Some(len))?;
+        let map = StructSerializer::new(output, Some(len), config, depth + 1)?;
         Ok(Self { inner: map })
     }
 }
@@ -207,6 +250,7 @@ impl<W: WWrite> ser::SerializeStructVariant for StructVariantSerializer<W> {
 
     #[inline]
     fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        self.inner.debug_assert_len();
         // Close the inner struct map `}` and the outer map `}`.
         self.inner.output.write_right_bracket()?;
         self.inner.output.write_right_bracket()?;
@@ -214,20 +258,98 @@ impl<W: WWrite> ser::SerializeStructVariant for StructVariantSerializer<W> {
     }
 }
 
+/// The name of the struct `serde_json` passes a `Number` in with its
+/// `arbitrary_precision` feature. Its only field holds the number's text.
+pub(crate) const SERDE_JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
 pub struct MapSerializer<W: WWrite> {
     output: W,
     is_first: bool,
+    config: Config,
+    /// Set for a [`SERDE_JSON_NUMBER_TOKEN`] struct, written as a bare number
+    /// instead of an object.
+    number: bool,
+    /// The depth of the values: the number of arrays and objects they are
+    /// nested in, this one included.
+    depth: usize,
+    /// The `len` hint, checked against the number of struct fields.
+    #[cfg(debug_assertions)]
+    len: Option<usize>,
+    #[cfg(debug_assertions)]
+    fields: usize,
 }
 
 impl<W: WWrite> MapSerializer<W> {
     #[inline]
-    pub fn new(mut output: W, _len: Option<usize>) -> Result<Self, Error> {
+    pub fn new(
+        mut output: W,
+        len: Option<usize>,
+        config: Config,
+        depth: usize,
+    ) -> Result<Self, Error> {
+        #[cfg(not(debug_assertions))]
+        let _ = len;
         output.write_left_bracket()?;
+        output.nested(depth);
         Ok(Self {
             output,
             is_first: true,
+            config,
+            number: false,
+            depth,
+            #[cfg(debug_assertions)]
+            len,
+            #[cfg(debug_assertions)]
+            fields: 0,
         })
     }
+
+    /// Prepares to write a [`SERDE_JSON_NUMBER_TOKEN`] struct as a bare number.
+    #[inline]
+    fn number(output: W, config: Config, depth: usize) -> Self {
+        Self {
+            output,
+            is_first: true,
+            config,
+            number: true,
+            depth,
+            #[cfg(debug_assertions)]
+            len: Some(1),
+            #[cfg(debug_assertions)]
+            fields: 0,
+        }
+    }
+
+    /// Checks that a struct had as many fields as its `len` hint said.
+    ///
+    /// Nothing here relies on the hint, but a mismatch means a broken
+    /// `Serialize` impl that other formats may choke on.
+    #[inline]
+    pub(crate) fn debug_assert_len(&self) {
+        #[cfg(debug_assertions)]
+        if let Some(len) = self.len {
+            debug_assert_eq!(
+                self.fields, len,
+                "struct serialized a different number of fields than its `len` hint"
+            );
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn output(&self) -> &W {
+        &self.output
+    }
+
+    /// Closes the map and returns the underlying writer.
+    #[inline]
+    pub(crate) fn finish(mut self) -> Result<W, Error> {
+        if !self.number {
+            self.output.write_right_bracket()?;
+        }
+        Ok(self.output)
+    }
 }
 
 impl<W: WWrite> ser::SerializeMap for MapSerializer<W> {
@@ -245,9 +367,15 @@ impl<W: WWrite> ser::SerializeMap for MapSerializer<W> {
         }
         // Write the key, quoted and escaped.
         self.output.write_quote()?;
-        key.serialize(KeySerializerNoQuotes {
-            output: self.output.escape(),
-        })?;
+        if self.config.unicode_escape_non_ascii {
+            key.serialize(KeySerializerNoQuotes {
+                output: UnicodeEscape::new(&mut self.output),
+            })?;
+        } else {
+            key.serialize(KeySerializerNoQuotes {
+                output: self.output.escape(),
+            })?;
+        }
         self.output.write_quote()?;
         self.output.write_colon()?;
         self.is_first = false;
@@ -262,12 +390,14 @@ impl<W: WWrite> ser::SerializeMap for MapSerializer<W> {
         value.serialize(JsonSerializer {
             output: self.output.as_mut(),
             is_top_level_value: false,
+            config: self.config,
+            depth: self.depth,
         })
     }
 
     #[inline]
-    fn end(mut self) -> Result<Self::Ok, Self::Error> {
-        self.output.write_right_bracket()?;
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()?;
         Ok(())
     }
 }
@@ -285,12 +415,26 @@ impl<W: WWrite> ser::SerializeStruct for StructSerializer<W> {
         T: ?Sized + Serialize,
     {
         use ser::SerializeMap as _;
+        #[cfg(debug_assertions)]
+        {
+            self.fields += 1;
+        }
+        if self.number {
+            // The number's text, written without quotes.
+            return value.serialize(JsonSerializer {
+                output: self.output.as_mut(),
+                is_top_level_value: true,
+                config: self.config,
+                depth: self.depth,
+            });
+        }
         // A struct field is just a map entry.
         self.serialize_entry(key, value)
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.debug_assert_len();
         ser::SerializeMap::end(self)
     }
 }
@@ -299,6 +443,9 @@ pub struct JsonSerializer<W: WWrite> {
     pub(crate) output: W,
     /// If true, strings are not quoted or escaped.
     pub(crate) is_top_level_value: bool,
+    pub(crate) config: Config,
+    /// The number of arrays and objects the value is nested in.
+    pub(crate) depth: usize,
 }
 
 macro_rules! inner_integer {
@@ -366,9 +513,21 @@ impl<W: WWrite> serde::Serializer for JsonSerializer<W> {
     type SerializeStruct = StructSerializer<W>;
     type SerializeStructVariant = StructVariantSerializer<W>;
 
-    defer_integer_n_bool_to_write! {}
+    inner_integer! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128
+    }
     defer_float_to_write_or_not_finite! {}
 
+    #[inline]
+    fn serialize_bool(mut self, v: bool) -> Result<Self::Ok, Self::Error> {
+        if self.config.bool_as_int {
+            self.output.write_integer(u8::from(v))?;
+        } else {
+            self.output.write_bool(v)?;
+        }
+        Ok(())
+    }
+
     #[inline]
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
         // A char encoded as UTF-8 takes 4 bytes at most.
@@ -384,28 +543,38 @@ impl<W: WWrite> serde::Serializer for JsonSerializer<W> {
             Ok(self.output.write_str(v)?)
         } else {
             self.output.write_quote()?;
-            self.output.escape().write_str(v)?;
+            if self.config.unicode_escape_non_ascii {
+                UnicodeEscape::new(&mut self.output).write_str(v)?;
+            } else {
+                self.output.escape().write_str(v)?;
+            }
             Ok(self.output.write_quote()?)
         }
     }
 
     #[inline]
     fn serialize_bytes(mut self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        // Written as an array, without a `SeqSerializer`.
+        self.output.nested(self.depth + 1);
         Ok(self.output.write_byte_array(v)?)
     }
 
     #[inline]
     fn collect_str<T>(mut self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: ?Sized + std::fmt::Display,
+        T: ?Sized + core::fmt::Display,
     {
         if self.is_top_level_value {
             // Do not quote or escape top-level strings from the form
             // serializer.
-            Ok(write!(self.output, "{value}")?)
+            Ok(self.output.write_display(value)?)
         } else {
             self.output.write_quote()?;
-            write!(self.output.escape(), "{value}")?;
+            if self.config.unicode_escape_non_ascii {
+                UnicodeEscape::new(&mut self.output).write_display(value)?;
+            } else {
+                self.output.escape().write_display(value)?;
+            }
             Ok(self.output.write_quote()?)
         }
     }
@@ -433,12 +602,26 @@ impl<W: WWrite> serde::Serializer for JsonSerializer<W> {
     #[inline]
     fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + Serialize,
     {
+        #[cfg(feature = "debug-verify")]
+        if self.config.verifying {
+            // Compared with `serde_json`, which writes raw JSON as a string.
+            return value.serialize(self);
+        }
+        if name == RAW_TOKEN {
+            // Raw JSON, like a number literal: write its text as is, without quotes.
+            return value.serialize(JsonSerializer {
+                output: self.output,
+                is_top_level_value: true,
+                config: self.config,
+                depth: self.depth,
+            });
+        }
         value.serialize(self)
     }
 
@@ -475,7 +658,7 @@ impl<W: WWrite> serde::Serializer for JsonSerializer<W> {
 
     #[inline]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        SeqSerializer::new(self.output, len)
+        SeqSerializer::new(self.output, len, self.config, self.depth + 1)
     }
 
     #[inline]
@@ -500,21 +683,28 @@ impl<W: WWrite> serde::Serializer for JsonSerializer<W> {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        TupleVariantSerializer::new(self.output, variant, len)
+        TupleVariantSerializer::new(self.output, variant, len, self.config, self.depth + 1)
     }
 
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        MapSerializer::new(self.output, len)
+        MapSerializer::new(self.output, len, self.config, self.depth + 1)
     }
 
     #[inline]
     fn serialize_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        StructSerializer::new(self.output, Some(len))
+        if name == SERDE_JSON_NUMBER_TOKEN {
+            return Ok(StructSerializer::number(
+                self.output,
+                self.config,
+                self.depth,
+            ));
+        }
+        StructSerializer::new(self.output, Some(len), self.config, self.depth + 1)
     }
 
     #[inline]
@@ -525,11 +715,15 @@ impl<W: WWrite> serde::Serializer for JsonSerializer<W> {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        StructVariantSerializer::new(self.output, variant, len)
+        StructVariantSerializer::new(self.output, variant, len, self.config, self.depth + 1)
     }
 }
 
-pub struct KeySerializerNoQuotes<W: WWrite> {
+/// Serializes a key, with no quotes around it.
+///
+/// Inside JSON, keys are written through an escaped writer, which can't be
+/// escaped again, so this only needs [`WWritePrimitives`].
+pub struct KeySerializerNoQuotes<W: WWritePrimitives> {
     pub(crate) output: W,
 }
 
@@ -559,7 +753,7 @@ macro_rules! defer_float_to_write_or_err_not_finite {
     };
 }
 
-impl<W: WWrite> ser::Serializer for KeySerializerNoQuotes<W> {
+impl<W: WWritePrimitives> ser::Serializer for KeySerializerNoQuotes<W> {
     type Ok = ();
     type Error = crate::error::Error;
 
@@ -576,9 +770,9 @@ impl<W: WWrite> ser::Serializer for KeySerializerNoQuotes<W> {
     #[inline]
     fn collect_str<T>(mut self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: ?Sized + std::fmt::Display,
+        T: ?Sized + core::fmt::Display,
     {
-        Ok(write!(self.output, "{value}")?)
+        Ok(self.output.write_display(value)?)
     }
 
     #[inline]
@@ -625,6 +819,7 @@ mod tests {
     use crate::write::PercentEncoding;
     use serde::Serialize;
     use std::collections::{BTreeMap, HashMap};
+    use std::prelude::rust_2024::*;
 
     fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
         let mut buf = String::with_capacity(128);
@@ -632,6 +827,8 @@ mod tests {
         let serializer = JsonSerializer {
             output: writer,
             is_top_level_value: false,
+            config: Config::default(),
+            depth: 0,
         };
         value.serialize(serializer)?;
         Ok(buf)
@@ -643,11 +840,34 @@ mod tests {
         let serializer = JsonSerializer {
             output: writer,
             is_top_level_value: true,
+            config: Config::default(),
+            depth: 0,
         };
         value.serialize(serializer)?;
         Ok(buf)
     }
 
+    #[test]
+    fn test_depth() {
+        use serde::Serializer as _;
+
+        let serializer = |depth| JsonSerializer {
+            output: PercentEncoding::new(String::new()),
+            is_top_level_value: false,
+            config: Config::default(),
+            depth,
+        };
+        // Containers count one level more than the value they are.
+        assert_eq!(serializer(0).serialize_seq(None).unwrap().depth, 1);
+        assert_eq!(serializer(1).serialize_map(None).unwrap().depth, 2);
+        assert_eq!(serializer(0).serialize_struct("S", 0).unwrap().depth, 1);
+        // `{"variant":[...]}` nests the elements twice.
+        let variant = serializer(0)
+            .serialize_tuple_variant("E", 0, "V", 0)
+            .unwrap();
+        assert_eq!(variant.inner.depth, 2);
+    }
+
     #[test]
     #[allow(clippy::approx_constant)]
     fn test_primitives() {
@@ -729,6 +949,46 @@ mod tests {
         );
     }
 
+    /// A struct that claims one more field than it serializes.
+    struct WrongLen;
+
+    impl Serialize for WrongLen {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeStruct as _;
+            let mut s = serializer.serialize_struct("WrongLen", 2)?;
+            s.serialize_field("a", &1)?;
+            s.end()
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic = "different number of fields"]
+    fn test_struct_len_mismatch() {
+        let _ = to_string(&WrongLen);
+    }
+
+    #[test]
+    fn test_struct_len_with_skipped_fields() {
+        #[derive(Serialize)]
+        struct Skips {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            a: Option<u8>,
+            #[serde(skip)]
+            _b: u8,
+            c: u8,
+        }
+        assert_eq!(
+            to_string(&Skips {
+                a: None,
+                _b: 0,
+                c: 1
+            })
+            .unwrap(),
+            "%7B%22c%22%3A1%7D"
+        );
+    }
+
     #[derive(Serialize)]
     enum MyEnum {
         Unit,
@@ -755,9 +1015,173 @@ mod tests {
         );
     }
 
+    #[derive(Serialize)]
+    #[serde(tag = "type", content = "c")]
+    enum Adjacent {
+        Unit,
+        Newtype(u32),
+        Struct { a: u32 },
+    }
+
+    #[test]
+    fn test_adjacently_tagged_enum() {
+        assert_eq!(
+            to_string(&Adjacent::Unit).unwrap(),
+            "%7B%22type%22%3A%22Unit%22%7D"
+        );
+        assert_eq!(
+            to_string(&Adjacent::Newtype(7)).unwrap(),
+            "%7B%22type%22%3A%22Newtype%22%2C%22c%22%3A7%7D"
+        );
+        assert_eq!(
+            to_string(&Adjacent::Struct { a: 1 }).unwrap(),
+            "%7B%22type%22%3A%22Struct%22%2C%22c%22%3A%7B%22a%22%3A1%7D%7D"
+        );
+    }
+
+    /// Asserts that `value` decodes to exactly what `serde_json` writes.
+    fn assert_matches_serde_json<T: Serialize>(value: &T) {
+        let encoded = to_string(value).unwrap();
+        let decoded = percent_encoding::percent_decode_str(&encoded)
+            .decode_utf8()
+            .unwrap();
+        assert_eq!(decoded, serde_json::to_string(value).unwrap());
+    }
+
+    #[test]
+    fn test_flatten() {
+        #[derive(Serialize)]
+        struct Inner {
+            a: u8,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            b: Option<f64>,
+        }
+
+        #[derive(Serialize)]
+        enum Choice {
+            Left(Inner),
+            Right { x: i64 },
+            Unit,
+        }
+
+        #[derive(Serialize)]
+        struct WithMap {
+            id: u32,
+            #[serde(flatten)]
+            extra: BTreeMap<String, serde_json::Value>,
+            last: bool,
+        }
+
+        #[derive(Serialize)]
+        struct WithEnum {
+            #[serde(flatten)]
+            choice: Choice,
+            #[serde(flatten, skip_serializing_if = "Option::is_none")]
+            inner: Option<Inner>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            list: Vec<u128>,
+        }
+
+        assert_matches_serde_json(&WithMap {
+            id: 1,
+            extra: BTreeMap::from([
+                ("big".into(), serde_json::json!(u64::MAX)),
+                ("neg".into(), serde_json::json!(-5)),
+                ("float".into(), serde_json::json!(0.1)),
+                ("nested".into(), serde_json::json!({"k": [1, "two", null]})),
+                ("quote\"d".into(), serde_json::json!("line\nbreak")),
+            ]),
+            last: true,
+        });
+        assert_matches_serde_json(&WithMap {
+            id: 2,
+            extra: BTreeMap::new(),
+            last: false,
+        });
+        assert_matches_serde_json(&WithEnum {
+            choice: Choice::Left(Inner { a: 1, b: Some(2.5) }),
+            inner: Some(Inner { a: 3, b: None }),
+            list: vec![u128::MAX],
+        });
+        assert_matches_serde_json(&WithEnum {
+            choice: Choice::Right { x: i64::MIN },
+            inner: None,
+            list: vec![],
+        });
+        assert_matches_serde_json(&vec![
+            WithEnum {
+                choice: Choice::Unit,
+                inner: None,
+                list: vec![],
+            },
+            WithEnum {
+                choice: Choice::Left(Inner { a: 0, b: None }),
+                inner: None,
+                list: vec![1],
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let bytes = serde_bytes::Bytes::new(&[1, 2, 255]);
+        assert_eq!(to_string(&bytes).unwrap(), "%5B1%2C2%2C255%5D");
+        // `serialize_bytes` and a plain sequence of `u8` agree.
+        assert_eq!(
+            to_string(&bytes).unwrap(),
+            to_string(&vec![1u8, 2, 255]).unwrap()
+        );
+
+        let empty = serde_bytes::ByteBuf::new();
+        assert_eq!(to_string(&empty).unwrap(), "%5B%5D");
+
+        // Bytes nested in a sequence.
+        let nested = vec![serde_bytes::Bytes::new(b"a"), serde_bytes::Bytes::new(b"")];
+        assert_eq!(to_string(&nested).unwrap(), "%5B%5B97%5D%2C%5B%5D%5D");
+    }
+
+    #[test]
+    fn test_128_bit_integer_keys() {
+        let map = HashMap::from([(-1i128 << 100, "a")]);
+        assert_eq!(
+            to_string(&map).unwrap(),
+            "%7B%22-1267650600228229401496703205376%22%3A%22a%22%7D"
+        );
+
+        let map = HashMap::from([(u128::MAX, "b")]);
+        assert_eq!(
+            to_string(&map).unwrap(),
+            "%7B%22340282366920938463463374607431768211455%22%3A%22b%22%7D"
+        );
+    }
+
     #[test]
     fn test_invalid_key() {
         let map = HashMap::from([([1], 3)]);
         to_string(&map).unwrap_err();
     }
+
+    #[test]
+    fn test_null_keys() {
+        use crate::error::ErrorKind;
+
+        let map = HashMap::from([(None::<String>, 1)]);
+        let err = to_string(&map).unwrap_err();
+        assert!(err.matches(ErrorKind::KeyMustBeAString));
+        assert_eq!(err.unexpected_type(), Some("Option::<T>::None"));
+        assert_eq!(
+            err.to_string(),
+            "Map key must be a string, but got Option::<T>::None"
+        );
+
+        let map = HashMap::from([((), 1)]);
+        let err = to_string(&map).unwrap_err();
+        assert!(err.matches(ErrorKind::KeyMustBeAString));
+        assert_eq!(err.unexpected_type(), Some("()"));
+        assert_eq!(err.to_string(), "Map key must be a string, but got ()");
+
+        // `Some` keys are written as the inner value.
+        let map = HashMap::from([(Some("a".to_owned()), 1)]);
+        assert_eq!(to_string(&map).unwrap(), "%7B%22a%22%3A1%7D");
+    }
 }
@@ -34,6 +34,14 @@
 //! 4.  **Separators**: Key-value pairs are joined by `&`, and each key is separated
 //!     from its value by `=`.
 //!
+//! ### Byte Buffers
+//!
+//! JSON has no byte string type. Bytes handed to `serialize_bytes` (for example, a
+//! field marked `#[serde(with = "serde_bytes")]`) are written as a JSON array of
+//! integers, exactly like a `Vec<u8>`: `[104,105]`. If the receiving API expects
+//! base64 or hex instead, encode the field explicitly with a wrapper type or a
+//! `#[serde(with = "...")]` module of your own.
+//!
 //! ---
 //!
 //! ### A Note on Compatibility
@@ -116,17 +124,159 @@
 //! assert_eq!(encoded, expected_string);
 //! ```
 
-use std::fmt::{Display, Write};
-
-use error::{Error, top_level_must_be_object};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// The tests use `std` even when the crate itself doesn't.
+#[cfg(all(test, not(feature = "std")))]
+#[macro_use]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+// Lets the derive macros refer to `::serde_metaform` in this crate's own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as serde_metaform;
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Write};
+
+use error::{Error, nested_value_in_flat_mode, poisoned, top_level_must_be_object};
+#[cfg(feature = "alloc")]
+use flat::FlatValueSerializer;
 use json::{JsonSerializer, KeySerializerNoQuotes};
 use serde::Serialize;
-use write::PercentEncoding;
-
+#[cfg(feature = "tracing")]
+use write::Counting;
+#[cfg(feature = "alloc")]
+use write::{AsciiBytes, Plain};
+use write::{Pending, Stringify, WWrite, WWritePrimitives};
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "alloc")]
+mod batch;
+#[cfg(feature = "bigdecimal")]
+pub mod bigdecimal;
+mod encoder;
 pub mod error;
+#[cfg(feature = "alloc")]
+mod flat;
+#[cfg(feature = "heapless")]
+pub mod heapless;
 mod json;
+#[cfg(feature = "derive")]
+mod pairs;
+#[cfg(feature = "pool")]
+pub mod pool;
+mod raw;
+#[cfg(all(test, feature = "alloc"))]
+mod serde_compat;
+mod skip;
+#[cfg(feature = "alloc")]
+mod sorted;
+#[cfg(feature = "alloc")]
+mod stats;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+#[cfg(feature = "timestamp")]
+mod timestamp;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "url")]
+pub mod url;
+mod validate;
+#[cfg(feature = "debug-verify")]
+mod verify;
 mod write;
 
+#[cfg(feature = "alloc")]
+pub use batch::{BatchSerializer, batch_serialize};
+pub use encoder::FormEncoder;
+#[cfg(feature = "derive")]
+pub use pairs::IntoFormPairs;
+pub use raw::RawJson;
+#[cfg(feature = "derive")]
+pub use serde_metaform_derive::IntoFormPairs;
+/// Catches types that can't be a payload at compile time, not when
+/// [`to_string`] fails.
+///
+/// A payload must serialize as an object: a struct with named fields, a map,
+/// or an enum whose variants all carry data. Unit and tuple structs, and enums
+/// with unit variants, are rejected. Newtypes, `#[serde(transparent)]` and
+/// `#[serde(into = "...")]` hand serialization over to another type, so they
+/// are let through, and still checked when serialized.
+///
+/// # Example
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_metaform::assert_top_level_struct;
+///
+/// #[assert_top_level_struct]
+/// #[derive(Serialize)]
+/// enum Event {
+///     Moved { x: i32, y: i32 },
+///     Renamed(String),
+/// }
+///
+/// assert_eq!(serde_metaform::to_string(&Event::Renamed("a".into())).unwrap(), "Renamed=a");
+/// ```
+///
+/// ```rust,compile_fail
+/// use serde::Serialize;
+/// use serde_metaform::assert_top_level_struct;
+///
+/// #[assert_top_level_struct]
+/// #[derive(Serialize)]
+/// struct Point(i32, i32); // error: a tuple struct serializes as a sequence
+/// ```
+///
+/// ```rust,compile_fail
+/// use serde::Serialize;
+/// use serde_metaform::assert_top_level_struct;
+///
+/// #[assert_top_level_struct]
+/// #[derive(Serialize)]
+/// enum Status {
+///     Active { since: u64 },
+///     Pending, // error: variant `Pending` serializes as a bare string
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use serde_metaform_derive::assert_top_level_struct;
+pub use skip::DefaultSkip;
+#[cfg(feature = "alloc")]
+pub use sorted::SortedMap;
+#[cfg(feature = "alloc")]
+pub use stats::{SerializeStats, to_string_with_stats};
+#[cfg(feature = "test-utils")]
+pub use test_utils::{PairDiff, diff};
+#[cfg(feature = "timestamp")]
+pub use timestamp::TimestampRfc3339;
+pub use validate::is_valid_form_string;
+pub use write::{EscapingPercentEncodingWrite, PercentEncoding};
+
+/// Used by the derive macros. Not public API.
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use erased_serde;
+    pub use serde;
+}
+
+/// The `Content-Type` of a "Form + JSON" body.
+///
+/// The payload is sent as a regular form body; only the values are JSON.
+pub const CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
 /// Serializes the given data structure into the provided writer.
 ///
 /// This is the most flexible serialization function, allowing for direct streaming
@@ -158,6 +308,7 @@ where
 /// # Errors
 ///
 /// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
 #[inline]
 pub fn to_string<T>(value: &T) -> Result<String, Error>
 where
@@ -168,21 +319,364 @@ where
     Ok(writer)
 }
 
+/// Serializes the given data structure as a `String`, along with the
+/// [`CONTENT_TYPE`] to send it with.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Auth { access_token: &'static str }
+///
+/// let (body, content_type) =
+///     serde_metaform::to_string_with_content_type(&Auth { access_token: "abc" }).unwrap();
+/// assert_eq!(body, "access_token=abc");
+/// assert_eq!(content_type, "application/x-www-form-urlencoded");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_string_with_content_type<T>(value: &T) -> Result<(String, &'static str), Error>
+where
+    T: ?Sized + Serialize,
+{
+    Ok((to_string(value)?, CONTENT_TYPE))
+}
+
+/// Serializes the given data structure as a `String`, along with the value of
+/// an `Authorization` header carrying `token`.
+///
+/// The header value is `Bearer <token>`, as Meta's Graph API expects next to
+/// the form body. `token` is used as is.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Message { to: &'static str }
+///
+/// let (body, authorization) =
+///     serde_metaform::to_string_with_auth(&Message { to: "123" }, "abc").unwrap();
+/// assert_eq!(body, "to=123");
+/// assert_eq!(authorization, "Bearer abc");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_string_with_auth<T>(value: &T, token: &str) -> Result<(String, String), Error>
+where
+    T: ?Sized + Serialize,
+{
+    let body = to_string(value)?;
+    let mut authorization = String::with_capacity("Bearer ".len() + token.len());
+    authorization.push_str("Bearer ");
+    authorization.push_str(token);
+    Ok((body, authorization))
+}
+
+/// Appends the serialized form of `value` to an existing payload.
+///
+/// If `existing` is non-empty, the new pairs are separated from it by `&`. A
+/// value with no pairs, such as an empty struct, leaves `existing` unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Auth { access_token: &'static str }
+///
+/// #[derive(Serialize)]
+/// struct Page { limit: u32 }
+///
+/// let mut body = String::new();
+/// serde_metaform::append_to_string(&mut body, &Auth { access_token: "abc" }).unwrap();
+/// serde_metaform::append_to_string(&mut body, &Page { limit: 25 }).unwrap();
+/// assert_eq!(body, "access_token=abc&limit=25");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+/// `existing` is left unchanged in that case.
+#[cfg(feature = "alloc")]
+pub fn append_to_string<T>(existing: &mut String, value: &T) -> Result<(), Error>
+where
+    T: ?Sized + Serialize,
+{
+    let start = existing.len();
+    if start > 0 {
+        existing.push('&');
+    }
+    let result = to_writer(&mut *existing, value);
+    // Drop the separator if nothing followed it, or everything on error.
+    if result.is_err() || existing.len() == start + 1 {
+        existing.truncate(start);
+    }
+    result
+}
+
+/// Returns the length in bytes of the serialized form of `value`.
+///
+/// The value is serialized into a sink that only counts bytes, so nothing is
+/// allocated. This is handy for a `Content-Length` header, and is what
+/// [`to_string_exact`] uses to size its buffer.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+#[inline]
+pub fn serialized_len<T>(value: &T) -> Result<usize, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut counter = write::Counter::default();
+    to_writer(&mut counter, value)?;
+    Ok(counter.len())
+}
+
+/// Serializes the given data structure as a `String` allocated to the exact size.
+///
+/// The value is serialized twice: once by [`serialized_len`] to measure the
+/// output, then into a `String` with exactly that capacity, which never has to
+/// grow.
+///
+/// [`to_string`] grows its buffer as it goes, copying the output on every
+/// reallocation and leaving up to half of the final buffer unused. For large
+/// payloads, or when the string is kept around, that costs more than the extra
+/// pass. For small payloads, the extra pass usually costs more.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_string_exact<T>(value: &T) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = String::with_capacity(serialized_len(value)?);
+    to_writer(&mut writer, value)?;
+    Ok(writer)
+}
+
 /// Serializes the given data structure as a vector of bytes (`Vec<u8>`).
 ///
-/// This is a convenience function that serializes to a `String` via [`to_string`]
-/// and then converts it to a byte vector.
+/// The output is the same as [`to_string`]'s. Since it is always ASCII, it is
+/// written to the vector directly.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_vec<T>(value: &T) -> Result<FormBytes, Error>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_capacity(value, 128)
+}
+
+/// A serialized payload as bytes, as returned by [`to_vec`] and
+/// [`to_bytes_with_capacity`]. It is always ASCII.
+#[cfg(feature = "alloc")]
+pub type FormBytes = Vec<u8>;
+
+/// Serializes the given data structure as bytes, into a vector allocated with
+/// room for `capacity` bytes.
+///
+/// Like [`to_vec`], but with a capacity of your choosing. A good estimate, such
+/// as the length of a previous payload of the same type, saves the vector from
+/// growing while it is written. For the exact length, see [`serialized_len`].
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Point { x: i32, y: i32 }
+///
+/// let bytes = serde_metaform::to_bytes_with_capacity(&Point { x: 1, y: 2 }, 16).unwrap();
+/// assert_eq!(bytes, b"x=1&y=2");
+/// assert!(bytes.capacity() >= 16);
+/// ```
 ///
 /// # Errors
 ///
 /// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+#[inline]
+pub fn to_bytes_with_capacity<T>(value: &T, capacity: usize) -> Result<FormBytes, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut bytes = Vec::with_capacity(capacity);
+    to_writer(AsciiBytes::new(&mut bytes), value)?;
+    Ok(bytes)
+}
+
+/// Serializes the given data structure into a caller-provided buffer.
+///
+/// Nothing is allocated on the heap unless serialization fails with a custom
+/// error message. On success, returns the number of bytes written to the start
+/// of `buf`.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Point { x: i32, y: i32 }
+///
+/// let mut buf = [0u8; 16];
+/// let len = serde_metaform::to_slice(&mut buf, &Point { x: 1, y: 2 }).unwrap();
+/// assert_eq!(&buf[..len], b"x=1&y=2");
+///
+/// let err = serde_metaform::to_slice(&mut buf[..4], &Point { x: 1, y: 2 }).unwrap_err();
+/// assert_eq!(err.needed_len(), Some(7));
+/// ```
+///
+/// # Errors
+///
+/// If the payload doesn't fit, returns an error whose
+/// [`needed_len`](Error::needed_len) is the size of the whole payload. The
+/// contents of `buf` are unspecified in that case. Otherwise, see
+/// [`to_writer`] for details.
 #[inline]
-pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+pub fn to_slice<T>(buf: &mut [u8], value: &T) -> Result<usize, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = write::SliceWrite::new(buf);
+    to_writer(&mut writer, value)?;
+    if writer.len() > writer.capacity() {
+        return Err(error::buffer_too_small(writer.len(), writer.capacity()));
+    }
+    Ok(writer.len())
+}
+
+/// Serializes an iterator of key-value pairs as a `String`.
+///
+/// The output is the same as collecting the pairs into a map and calling
+/// [`to_string`], but no map is built. Pairs are written in iteration order,
+/// and repeated keys are written as many times as they occur.
+///
+/// # Example
+///
+/// ```rust
+/// let fields = [("id", 1), ("count", 2)];
+/// let encoded = serde_metaform::to_string_from_iter(fields).unwrap();
+/// assert_eq!(encoded, "id=1&count=2");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serializing a value fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+pub fn to_string_from_iter<I, K, V>(iter: I) -> Result<String, Error>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Serialize,
+{
+    use serde::ser::{SerializeMap as _, Serializer as _};
+
+    let mut writer = String::with_capacity(128);
+    let mut map = Serializer::new(&mut writer).serialize_map(None)?;
+    for (key, value) in iter {
+        map.serialize_entry(key.as_ref(), &value)?;
+    }
+    map.end()?;
+    Ok(writer)
+}
+
+/// Serializes the given data structure without percent-encoding, for debugging
+/// only.
+///
+/// Keys and values are written as they are before percent-encoding, so the
+/// JSON in each value is readable: `tags=["a b"]` instead of
+/// `tags=%5B%22a%20b%22%5D`. This is meant for test assertions and logs.
+///
+/// **The output is not a valid payload.** It is not URL-safe, and a key or
+/// value containing `&` or `=` makes it ambiguous. Never send it anywhere.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+///
+/// let value = BTreeMap::from([("name", vec!["John Doe"])]);
+/// let raw = serde_metaform::to_string_raw(&value).unwrap();
+/// assert_eq!(raw, r#"name=["John Doe"]"#);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+#[cfg(feature = "alloc")]
+pub fn to_string_raw<T>(value: &T) -> Result<String, Error>
 where
     T: ?Sized + Serialize,
 {
-    let string = to_string(value)?;
-    Ok(string.into())
+    // Every key and value is encoded on its own, and the separators are never
+    // encoded, so decoding the whole payload at once undoes exactly the
+    // percent-encoding. The input was valid UTF-8, so the decoded text is too.
+    let encoded = to_string(value)?;
+    Ok(percent_encoding::percent_decode_str(&encoded)
+        .decode_utf8_lossy()
+        .into_owned())
+}
+
+/// Percent-encodes `s` as an RFC 3986 query component.
+///
+/// This is far less aggressive than the encoding used for form values: only
+/// characters that may not appear in a query at all are encoded, so delimiters
+/// like `?`, `/`, `=` and `&` are left as is. Use it for URLs that must follow
+/// RFC 3986 rather than form encoding.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_metaform::encode_rfc3986_query;
+///
+/// assert_eq!(
+///     encode_rfc3986_query("fields=id,name&q=a b#c"),
+///     "fields=id,name&q=a%20b%23c"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_rfc3986_query(s: &str) -> String {
+    percent_encoding::utf8_percent_encode(s, write::RFC3986_QUERY_ENCODE_SET).to_string()
+}
+
+/// Percent-encodes `s` as an RFC 5987 `value-chars`, for HTTP header parameters.
+///
+/// This is the encoding of extended parameters like `filename*` in a
+/// `Content-Disposition` header, as specified by
+/// [RFC 5987, section 3.2.1](https://www.rfc-editor.org/rfc/rfc5987#section-3.2.1):
+/// the `attr-char`s (letters, digits and ``! # $ & + - . ^ _ ` | ~``) are left
+/// as is, and every other byte of the UTF-8 encoding is percent-encoded. Prefix
+/// the result with the charset and language, as in `UTF-8''`.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_metaform::encode_rfc5987;
+///
+/// let header = format!("attachment; filename*=UTF-8''{}", encode_rfc5987("naïve report.pdf"));
+/// assert_eq!(header, "attachment; filename*=UTF-8''na%C3%AFve%20report.pdf");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_rfc5987(s: &str) -> String {
+    percent_encoding::utf8_percent_encode(s, write::RFC5987_ENCODE_SET).to_string()
 }
 
 /// Creates a displayable wrapper for a serializable type.
@@ -222,9 +716,9 @@ where
     V: ?Sized + Serialize,
 {
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let ser = Serializer::new(f);
-        self.value.serialize(ser).map_err(|_| std::fmt::Error)
+        self.value.serialize(ser).map_err(|_| core::fmt::Error)
     }
 }
 
@@ -235,75 +729,634 @@ where
 /// for serializing structs, maps, and enum variants.
 pub struct Serializer<W> {
     output: W,
-    is_first: bool,
+    /// The number of `key=value` pairs written so far.
+    pairs: usize,
+    /// The deepest JSON array or object in the values written so far.
+    max_depth: usize,
+    config: Config,
+    /// The pending key in flat mode, written once the value is known, or the
+    /// pending pair in transactional mode, written once it is complete.
+    #[cfg(feature = "alloc")]
+    pending: String,
+    /// Set when a pair failed partway through, leaving a fragment behind.
+    poisoned: bool,
+    #[cfg(feature = "tracing")]
+    trace: trace::PairTrace,
 }
 
-impl<W: Write> Serializer<W> {
-    /// Creates a new serializer that writes to the given `writer`.
+/// Encoding options applied to every value written by a [`Serializer`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Config {
+    /// Wrap every value in a JSON string.
+    pub(crate) force_json_string_values: bool,
+    /// Only allow scalar values, written like `serde_urlencoded` does.
+    pub(crate) flat_strict: bool,
+    /// Write booleans in JSON values as `1` and `0`.
+    pub(crate) bool_as_int: bool,
+    /// Write each pair to the output only once it is complete.
+    pub(crate) transactional: bool,
+    /// Write nothing for a top-level unit, unit struct or unit variant.
+    pub(crate) allow_empty_top_level: bool,
+    /// Write non-ASCII characters in JSON strings as `\uXXXX` escapes.
+    pub(crate) unicode_escape_non_ascii: bool,
+    /// Leave out pairs whose value is a [`DefaultSkip`] holding its default.
+    pub(crate) skip_default_values: bool,
+    /// Written verbatim between each key and its value.
+    pub(crate) kv_separator: &'static str,
+    /// Serializing the second copy of a value for the `serde_json` comparison.
+    #[cfg(feature = "debug-verify")]
+    pub(crate) verifying: bool,
+    /// Values can only be serialized once, so they are not cross-checked.
+    #[cfg(feature = "debug-verify")]
+    pub(crate) one_shot: bool,
+}
+
+impl Default for Config {
     #[inline]
-    pub fn new(writer: W) -> Self {
+    fn default() -> Self {
         Self {
-            output: writer,
-            is_first: true,
+            force_json_string_values: false,
+            flat_strict: false,
+            bool_as_int: false,
+            transactional: false,
+            allow_empty_top_level: false,
+            unicode_escape_non_ascii: false,
+            skip_default_values: false,
+            kv_separator: "=",
+            #[cfg(feature = "debug-verify")]
+            verifying: false,
+            #[cfg(feature = "debug-verify")]
+            one_shot: false,
         }
     }
+}
 
-    /// Unwraps the serializer, returning the underlying writer.
-    pub fn into_inner(self) -> W {
-        self.output
+impl Config {
+    #[inline]
+    pub(crate) fn set_kv_separator(&mut self, separator: &'static str) {
+        assert!(
+            !separator.contains('&'),
+            "key-value separator must not contain `&`, got {separator:?}"
+        );
+        // It is written verbatim, and the output must stay ASCII.
+        assert!(
+            separator.is_ascii(),
+            "key-value separator must be ASCII, got {separator:?}"
+        );
+        self.kv_separator = separator;
     }
 }
 
-impl<W: Write> serde::Serializer for Serializer<W> {
-    type Ok = ();
-    type Error = Error;
-
-    type SerializeMap = MapSerializer<W>;
-    type SerializeStruct = StructSerializer<W>;
-    type SerializeTupleVariant = TupleVariantSerializer<W>;
-    type SerializeStructVariant = StructVariantSerializer<W>;
-
-    // ---- T ----
+impl<W: Write> Serializer<W> {
+    /// Creates a new serializer that writes to the given `writer`.
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::default())
+    }
 
     #[inline]
-    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
-    where
-        T: ?Sized + Serialize,
-    {
-        value.serialize(self)
+    pub(crate) fn with_config(writer: W, config: Config) -> Self {
+        Self {
+            output: writer,
+            pairs: 0,
+            max_depth: 0,
+            config,
+            #[cfg(feature = "alloc")]
+            pending: String::new(),
+            poisoned: false,
+            #[cfg(feature = "tracing")]
+            trace: trace::PairTrace::new(),
+        }
     }
 
+    /// Serializes every value as a JSON string.
+    ///
+    /// Some backends expect all form values to be JSON strings, even numbers and
+    /// booleans. With this enabled, `count=1` is written as `count=%221%22`, and
+    /// compound values are written as their JSON text wrapped in a JSON string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Query { count: u32, exact: bool }
+    ///
+    /// let mut out = String::new();
+    /// let ser = Serializer::new(&mut out).force_json_string_values(true);
+    /// Query { count: 1, exact: true }.serialize(ser).unwrap();
+    /// assert_eq!(out, "count=%221%22&exact=%22true%22");
+    /// ```
     #[inline]
-    fn serialize_newtype_struct<T>(
-        self,
-        _name: &'static str,
-        value: &T,
-    ) -> Result<Self::Ok, Self::Error>
-    where
-        T: ?Sized + Serialize,
-    {
-        value.serialize(self)
+    pub fn force_json_string_values(mut self, force: bool) -> Self {
+        self.config.force_json_string_values = force;
+        self
     }
 
-    // ---- EMPTY ----
+    /// Serializes booleans in values as `1` and `0`.
+    ///
+    /// Some legacy endpoints expect booleans as integers. With this enabled,
+    /// `true` is written as `1` and `false` as `0`, both at the top level and
+    /// nested in JSON values. Keys are unaffected, and so is
+    /// [`flat_strict`](Self::flat_strict) output.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Flags { active: bool, history: Vec<bool> }
+    ///
+    /// let mut out = String::new();
+    /// let ser = Serializer::new(&mut out).bool_as_int(true);
+    /// Flags { active: true, history: vec![false, true] }.serialize(ser).unwrap();
+    /// assert_eq!(out, "active=1&history=%5B0%2C1%5D");
+    /// ```
+    #[inline]
+    pub fn bool_as_int(mut self, bool_as_int: bool) -> Self {
+        self.config.bool_as_int = bool_as_int;
+        self
+    }
 
-    /// Serializes a unit `()` or `Option::None` as an empty string.
+    /// Writes every non-ASCII character in JSON strings as a `\uXXXX` escape.
+    ///
+    /// Some strict parsers only accept ASCII inside JSON. With this enabled,
+    /// `"é"` is written as `"\u00e9"` before percent-encoding, and characters
+    /// above U+FFFF as a surrogate pair. This applies to strings and object
+    /// keys nested in JSON values. Top-level keys and string values aren't
+    /// JSON, so they are only percent-encoded as usual.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Profile { name: &'static str, tags: Vec<&'static str> }
+    ///
+    /// let mut out = String::new();
+    /// let ser = Serializer::new(&mut out).unicode_escape_non_ascii(true);
+    /// Profile { name: "José", tags: vec!["café"] }.serialize(ser).unwrap();
+    /// assert_eq!(out, "name=Jos%C3%A9&tags=%5B%22caf%5Cu00e9%22%5D");
+    /// ```
     #[inline]
-    fn serialize_unit(mut self) -> Result<Self::Ok, Self::Error> {
-        // Treat unit/None as empty string value
-        Ok(self.output.write_str("")?)
+    pub fn unicode_escape_non_ascii(mut self, escape: bool) -> Self {
+        self.config.unicode_escape_non_ascii = escape;
+        self
     }
 
-    /// Serializes a unit struct as an empty string.
+    /// Sets the separator written between each key and its value.
+    ///
+    /// The default is `=`. Some non-standard backends expect `:` or a space
+    /// instead. The separator is structural, so it is written verbatim, without
+    /// percent-encoding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `separator` contains `&`, which would make the pairs ambiguous,
+    /// or if it isn't ASCII, since the output always is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Point { x: i32, y: i32 }
+    ///
+    /// let mut out = String::new();
+    /// let ser = Serializer::new(&mut out).kv_separator(":");
+    /// Point { x: 1, y: 2 }.serialize(ser).unwrap();
+    /// assert_eq!(out, "x:1&y:2");
+    /// ```
     #[inline]
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        self.serialize_unit()
+    pub fn kv_separator(mut self, separator: &'static str) -> Self {
+        self.config.set_kv_separator(separator);
+        self
     }
 
-    /// Serializes `Option::None` as an empty string.
+    /// Writes plain `application/x-www-form-urlencoded` output.
+    ///
+    /// This lets the same types be sent both to APIs that expect this format and
+    /// to standard form endpoints. In flat mode, only scalar values (strings,
+    /// numbers, booleans and `None`) are allowed, and they are written exactly like
+    /// `serde_urlencoded` writes them:
+    ///
+    /// - Strings are not quoted, and spaces are written as `+`.
+    /// - `None` omits the pair entirely.
+    /// - Nested values (maps, sequences, structs, ...) are rejected with an error
+    ///   naming the key.
+    ///
+    /// This takes precedence over [`force_json_string_values`](Self::force_json_string_values).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search { q: &'static str, page: Option<u32> }
+    ///
+    /// let mut out = String::new();
+    /// let ser = Serializer::new(&mut out).flat_strict(true);
+    /// Search { q: "rust serde", page: None }.serialize(ser).unwrap();
+    /// assert_eq!(out, "q=rust+serde");
+    /// ```
+    ///
+    /// Flat mode buffers each key, so it needs the `alloc` feature.
+    #[cfg(feature = "alloc")]
     #[inline]
-    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        self.serialize_unit()
+    pub fn flat_strict(mut self, strict: bool) -> Self {
+        self.config.flat_strict = strict;
+        self
+    }
+
+    /// Writes each pair to the output only once it has been fully serialized.
+    ///
+    /// By default, pairs are streamed straight into the writer, so a value that
+    /// fails part-way leaves `key=` and a fragment of JSON behind. With this
+    /// enabled, each pair is first rendered into a scratch buffer, and a failed
+    /// pair leaves the output exactly as it was after the last complete one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use std::collections::BTreeMap;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Request { id: u32, extra: Vec<BTreeMap<(u8, u8), u8>> }
+    ///
+    /// let extra = vec![BTreeMap::from([((1, 2), 3)])];
+    /// let mut out = String::new();
+    /// let ser = Serializer::new(&mut out).transactional(true);
+    /// assert!(Request { id: 7, extra }.serialize(ser).is_err());
+    /// assert_eq!(out, "id=7");
+    /// ```
+    ///
+    /// The scratch buffer needs the `alloc` feature.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn transactional(mut self, transactional: bool) -> Self {
+        self.config.transactional = transactional;
+        self
+    }
+
+    /// Writes an empty payload for a top-level `()`, unit struct or unit variant.
+    ///
+    /// None of these has any fields to write, so by default they fail like any
+    /// other value that isn't an object. With this enabled, they serialize to
+    /// nothing instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Ping;
+    ///
+    /// assert!(serde_metaform::to_string(&Ping).is_err());
+    ///
+    /// let mut out = String::new();
+    /// Ping.serialize(Serializer::new(&mut out).allow_empty_top_level(true)).unwrap();
+    /// assert_eq!(out, "");
+    /// ```
+    #[inline]
+    pub fn allow_empty_top_level(mut self, allow: bool) -> Self {
+        self.config.allow_empty_top_level = allow;
+        self
+    }
+
+    /// Leaves out pairs whose value is a [`DefaultSkip`] holding its default.
+    ///
+    /// Only top-level fields and map entries are left out. Nested in a JSON
+    /// value, a `DefaultSkip` is always written, like the value it holds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Serialize;
+    /// use serde_metaform::{DefaultSkip, Serializer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Filter { name: DefaultSkip<String>, limit: DefaultSkip<u32> }
+    ///
+    /// let filter = Filter { name: DefaultSkip(String::new()), limit: DefaultSkip(10) };
+    /// let mut out = String::new();
+    /// filter.serialize(Serializer::new(&mut out).skip_default_values(true)).unwrap();
+    /// assert_eq!(out, "limit=10");
+    /// ```
+    #[inline]
+    pub fn skip_default_values(mut self, skip: bool) -> Self {
+        self.config.skip_default_values = skip;
+        self
+    }
+
+    /// Replaces the writer, keeping the options.
+    ///
+    /// The old writer is dropped, and the new serializer starts a fresh
+    /// payload. Use [`into_inner`](Self::into_inner) first to keep the old
+    /// writer.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    /// use serde::Serialize;
+    /// use serde_metaform::Serializer;
+    ///
+    /// let configured = Serializer::new(String::new()).bool_as_int(true);
+    ///
+    /// let mut out = String::new();
+    /// BTreeMap::from([("on", true)])
+    ///     .serialize(configured.with_writer(&mut out))
+    ///     .unwrap();
+    /// assert_eq!(out, "on=1");
+    /// ```
+    #[inline]
+    pub fn with_writer<W2: Write>(self, writer: W2) -> Serializer<W2> {
+        Serializer::with_config(writer, self.config)
+    }
+
+    /// Returns the number of `key=value` pairs written so far.
+    ///
+    /// Pairs omitted in [flat mode](Self::flat_strict) because their value is
+    /// `None` are not counted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde::ser::{SerializeMap, Serializer as _};
+    /// use serde_metaform::Serializer;
+    ///
+    /// let mut out = String::new();
+    /// let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+    /// assert_eq!(map.pairs_written(), 0);
+    /// map.serialize_entry("id", &1).unwrap();
+    /// map.serialize_entry("tags", &["a", "b"]).unwrap();
+    /// assert_eq!(map.pairs_written(), 2);
+    /// ```
+    #[inline]
+    pub fn pairs_written(&self) -> usize {
+        self.pairs
+    }
+
+    /// Writes a pair whose key is already percent-encoded.
+    ///
+    /// The key is written as is, saving the cost of encoding it again, for
+    /// instance when it comes from a cache. The value is serialized like any
+    /// other, following the serializer's options.
+    ///
+    /// The caller is responsible for `raw_key` being correctly encoded: it
+    /// must be ASCII, with no `&` or `=`, and every `%` must start a `%XX`
+    /// escape. This is only checked in debug builds. In
+    /// [flat mode](Self::flat_strict), it must be encoded the way flat keys
+    /// are, with spaces as `+`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serializing `value` fails, like `serialize_entry`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde::ser::{SerializeMap, Serializer as _};
+    /// use serde_metaform::Serializer;
+    ///
+    /// let mut out = String::new();
+    /// let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+    /// map.serialize_entry("id", &1).unwrap();
+    /// map.serialize_raw_pair("user%5Bname%5D", &"a b").unwrap();
+    /// assert_eq!(out, "id=1&user%5Bname%5D=a%20b");
+    /// ```
+    pub fn serialize_raw_pair<T>(&mut self, raw_key: &str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        debug_assert!(
+            raw_key.is_ascii()
+                && !raw_key.contains(['&', '='])
+                && validate::is_valid_component(raw_key),
+            "raw key is not percent-encoded: {raw_key:?}"
+        );
+        if self.poisoned {
+            return Err(poisoned());
+        }
+        if self.config.skip_default_values && skip::is_skipped_default(value) {
+            return Ok(());
+        }
+        #[cfg(feature = "tracing")]
+        if self.trace.on {
+            self.trace.set_raw_key(raw_key);
+        }
+        let result = self.write_raw_pair(raw_key, value);
+        self.poison_on_error(result)
+    }
+
+    #[inline]
+    fn write_raw_pair<T>(&mut self, raw_key: &str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        #[cfg(feature = "alloc")]
+        if self.config.flat_strict {
+            self.pending.clear();
+            self.pending.push_str(raw_key);
+            return value.serialize(FlatValueSerializer {
+                ser: self,
+                raw_key: true,
+            });
+        }
+
+        #[cfg(feature = "alloc")]
+        if self.config.transactional {
+            self.pending.clear();
+            write_raw_key(&mut self.pending, self.pairs, raw_key, self.config)?;
+            #[cfg(feature = "tracing")]
+            {
+                self.trace.key_len = self.pending.len();
+            }
+            return self.write_map_value(value);
+        }
+
+        write_raw_key(&mut self.output, self.pairs, raw_key, self.config)?;
+        #[cfg(feature = "tracing")]
+        {
+            self.trace.key_len =
+                usize::from(self.pairs > 0) + raw_key.len() + self.config.kv_separator.len();
+        }
+        self.write_map_value(value)
+    }
+
+    /// Ends a map or struct like `end`, but fails if no pair was written.
+    ///
+    /// Some APIs reject empty form bodies. Pairs left out in
+    /// [flat mode](Self::flat_strict) or by
+    /// [`skip_default_values`](Self::skip_default_values) don't count.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde::ser::{SerializeMap, Serializer as _};
+    /// use serde_metaform::Serializer;
+    ///
+    /// let mut out = String::new();
+    /// let map = Serializer::new(&mut out).serialize_map(None).unwrap();
+    /// assert!(map.end_non_empty().is_err());
+    ///
+    /// let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+    /// map.serialize_entry("id", &1).unwrap();
+    /// map.end_non_empty().unwrap();
+    /// assert_eq!(out, "id=1");
+    /// ```
+    #[inline]
+    pub fn end_non_empty(self) -> Result<(), Error> {
+        if self.pairs == 0 {
+            return Err(serde::ser::Error::custom("form body must not be empty"));
+        }
+        serde::ser::SerializeMap::end(self)
+    }
+
+    /// Unwraps the serializer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// Clears the error state after a failed pair, and starts a new payload.
+    ///
+    /// Once a key or value fails to serialize, the writer may hold part of a
+    /// pair, so every later key and value fails with
+    /// [`ErrorKind::Poisoned`](error::ErrorKind::Poisoned) rather than append
+    /// to it. After a reset, the next pair is written as the first one, without
+    /// a leading `&`. The writer is not touched: discarding what it already
+    /// holds is up to the caller. Use [`with_writer`](Self::with_writer) to
+    /// start over with a fresh writer instead.
+    ///
+    /// In [transactional](Self::transactional) mode, failed pairs leave nothing
+    /// behind, so the serializer is never poisoned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde::ser::{SerializeMap, Serializer as _};
+    /// use serde_metaform::Serializer;
+    ///
+    /// let mut out = String::new();
+    /// let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+    /// map.serialize_entry("id", &1).unwrap();
+    /// assert!(map.serialize_entry(&[1], &2).is_err());
+    /// assert!(map.serialize_entry("name", &"x").is_err());
+    ///
+    /// map.reset();
+    /// map.serialize_entry("name", &"x").unwrap();
+    /// assert_eq!(out, "id=1&name=x");
+    /// ```
+    #[inline]
+    pub fn reset(&mut self) {
+        self.pairs = 0;
+        self.max_depth = 0;
+        self.poisoned = false;
+        #[cfg(feature = "alloc")]
+        self.pending.clear();
+    }
+
+    /// Poisons the serializer if `result` is an error that may have left part
+    /// of a pair in the output.
+    #[inline]
+    fn poison_on_error(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        #[cfg(feature = "alloc")]
+        let clean = self.config.transactional;
+        #[cfg(not(feature = "alloc"))]
+        let clean = false;
+        if result.is_err() && !clean {
+            self.poisoned = true;
+        }
+        result
+    }
+
+    /// Writes nothing for an empty top-level value if that's allowed, and
+    /// fails with `got` otherwise.
+    #[inline]
+    fn empty_top_level(self, got: &'static str) -> Result<(), Error> {
+        if self.config.allow_empty_top_level {
+            Ok(())
+        } else {
+            Err(top_level_must_be_object(got))
+        }
+    }
+}
+
+impl<W: Write> serde::Serializer for Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeMap = MapSerializer<W>;
+    type SerializeStruct = StructSerializer<W>;
+    type SerializeTupleVariant = TupleVariantSerializer<W>;
+    type SerializeStructVariant = StructVariantSerializer<W>;
+
+    // ---- T ----
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    // ---- EMPTY ----
+
+    /// Fails, unless [`allow_empty_top_level`](Serializer::allow_empty_top_level)
+    /// is set, in which case nothing is written.
+    #[inline]
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.empty_top_level("()")
+    }
+
+    /// Fails like `serialize_unit`.
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.empty_top_level("UnitStruct")
+    }
+
+    /// Fails like `serialize_unit`.
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.empty_top_level("UnitVariant")
+    }
+
+    /// Serializes `Option::None` as an empty string.
+    #[inline]
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
     }
 
     // ---- OBJECT ----
@@ -322,41 +1375,83 @@ impl<W: Write> serde::Serializer for Serializer<W> {
     {
         use serde::ser::SerializeMap as _;
 
-        self.serialize_entry(variant, value)
+        self.serialize_entry(variant, value)?;
+        self.end()
     }
 
     /// Prepares to serialize a tuple enum variant as `variant=[...]`.
     #[inline]
     fn serialize_tuple_variant(
-        mut self,
+        self,
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        use serde::ser::SerializeMap as _;
+        if self.config.flat_strict {
+            return Err(nested_value_in_flat_mode(variant));
+        }
         // Write the key: `variant=`
-        self.serialize_key(variant)?;
+        let mut output = Pending::new(self.output, self.config.transactional);
+        write_key(&mut output, self.pairs, variant, self.config)?;
         // Prepare to write the value as a JSON array: `[...]`
-        let seq = json::SeqSerializer::new(PercentEncoding::new(self.output), Some(len))?;
-        Ok(TupleVariantSerializer { inner: seq })
+        let mut output = PercentEncoding::new(output);
+        let inner = if self.config.force_json_string_values {
+            output.write_quote()?;
+            TopLevelValue::Stringified(json::SeqSerializer::new(
+                Stringify::new(output),
+                Some(len),
+                self.config,
+                1,
+            )?)
+        } else {
+            TopLevelValue::Json(json::SeqSerializer::new(output, Some(len), self.config, 1)?)
+        };
+        Ok(TupleVariantSerializer {
+            inner,
+            #[cfg(feature = "debug-verify")]
+            config: self.config,
+        })
     }
 
     /// Prepares to serialize a struct enum variant as `variant={...}`.
     #[inline]
     fn serialize_struct_variant(
-        mut self,
+        self,
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        use serde::ser::SerializeMap as _;
+        if self.config.flat_strict {
+            return Err(nested_value_in_flat_mode(variant));
+        }
         // Write the key: `variant=`
-        self.serialize_key(variant)?;
+        let mut output = Pending::new(self.output, self.config.transactional);
+        write_key(&mut output, self.pairs, variant, self.config)?;
         // Prepare to write the value as a JSON object: `{...}`
-        let object = json::StructSerializer::new(PercentEncoding::new(self.output), Some(len))?;
-        Ok(StructVariantSerializer { inner: object })
+        let mut output = PercentEncoding::new(output);
+        let inner = if self.config.force_json_string_values {
+            output.write_quote()?;
+            TopLevelValue::Stringified(json::StructSerializer::new(
+                Stringify::new(output),
+                Some(len),
+                self.config,
+                1,
+            )?)
+        } else {
+            TopLevelValue::Json(json::StructSerializer::new(
+                output,
+                Some(len),
+                self.config,
+                1,
+            )?)
+        };
+        Ok(StructVariantSerializer {
+            inner,
+            #[cfg(feature = "debug-verify")]
+            config: self.config,
+        })
     }
 
     #[inline]
@@ -367,14 +1462,27 @@ impl<W: Write> serde::Serializer for Serializer<W> {
     #[inline]
     fn serialize_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        if name == json::SERDE_JSON_NUMBER_TOKEN {
+            // An `arbitrary_precision` number, just a primitive.
+            return Err(top_level_must_be_object("Number"));
+        }
         Ok(self)
     }
 
+    /// Fails like `serialize_str`, without formatting `value` first.
+    #[inline]
+    fn collect_str<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Display,
+    {
+        Err(top_level_must_be_object("str"))
+    }
+
     error_unsupported! {
-        top_level_must_be_object, [bool integers char str bytes array]
+        top_level_must_be_object, [bool integers floats char str bytes array]
     }
 }
 
@@ -390,37 +1498,199 @@ impl<W: Write> serde::ser::SerializeMap for MapSerializer<W> {
     where
         T: ?Sized + Serialize,
     {
-        if !self.is_first {
-            self.output.write_str("&")?;
+        if self.poisoned {
+            return Err(poisoned());
+        }
+        #[cfg(feature = "tracing")]
+        if self.trace.on {
+            self.trace.set_key(key);
         }
+        let result = self.write_map_key(key);
+        self.poison_on_error(result)
+    }
 
-        key.serialize(KeySerializerNoQuotes {
-            output: PercentEncoding::new(&mut self.output),
-        })?;
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.poisoned {
+            return Err(poisoned());
+        }
+        let result = self.write_map_value(value);
+        self.poison_on_error(result)
+    }
+
+    #[inline]
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), Self::Error>
+    where
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
+    {
+        if self.config.skip_default_values && !self.poisoned && skip::is_skipped_default(value) {
+            return Ok(());
+        }
+        self.serialize_key(key)?;
+        self.serialize_value(value)
+    }
 
-        self.output.write_str("=")?;
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        #[cfg(feature = "tracing")]
+        self.trace.finish(self.pairs);
         Ok(())
     }
+}
 
+impl<W: Write> MapSerializer<W> {
     #[inline]
-    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn write_map_key<T>(&mut self, key: &T) -> Result<(), Error>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(JsonSerializer {
-            output: PercentEncoding::new(&mut self.output),
-            is_top_level_value: true,
-        })?;
-        self.is_first = false;
-        Ok(())
+        #[cfg(feature = "alloc")]
+        if self.config.flat_strict {
+            // The pair is written along with the value, which may be `None`.
+            self.pending.clear();
+            return key.serialize(KeySerializerNoQuotes {
+                output: Plain::new(&mut self.pending),
+            });
+        }
+
+        #[cfg(feature = "alloc")]
+        if self.config.transactional {
+            self.pending.clear();
+            write_key(&mut self.pending, self.pairs, key, self.config)?;
+            #[cfg(feature = "tracing")]
+            {
+                self.trace.key_len = self.pending.len();
+            }
+            return Ok(());
+        }
+
+        #[cfg(feature = "tracing")]
+        if self.trace.on {
+            let mut output = Counting::new(&mut self.output);
+            write_key(&mut output, self.pairs, key, self.config)?;
+            self.trace.key_len = output.count();
+            return Ok(());
+        }
+
+        write_key(&mut self.output, self.pairs, key, self.config)
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn write_map_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        #[cfg(feature = "alloc")]
+        if self.config.flat_strict {
+            return value.serialize(FlatValueSerializer {
+                ser: self,
+                raw_key: false,
+            });
+        }
+
+        #[cfg(feature = "debug-verify")]
+        verify::check(value, self.config);
+
+        #[cfg(feature = "alloc")]
+        if self.config.transactional {
+            // On error, the pending pair is dropped by the next key.
+            let depth = write_value(&mut self.pending, value, self.config)?;
+            self.output.write_str(&self.pending)?;
+            self.max_depth = self.max_depth.max(depth);
+            self.pairs += 1;
+            #[cfg(feature = "tracing")]
+            if self.trace.on {
+                let value_len = self.pending.len() - self.trace.key_len;
+                self.trace.pair(value_len);
+            }
+            return Ok(());
+        }
+
+        #[cfg(feature = "tracing")]
+        if self.trace.on {
+            let mut output = Counting::new(&mut self.output);
+            let depth = write_value(&mut output, value, self.config)?;
+            let value_len = output.count();
+            self.max_depth = self.max_depth.max(depth);
+            self.pairs += 1;
+            self.trace.pair(value_len);
+            return Ok(());
+        }
+
+        let depth = write_value(&mut self.output, value, self.config)?;
+        self.pairs += 1;
+        self.max_depth = self.max_depth.max(depth);
         Ok(())
     }
 }
 
+/// Writes `key` and the key-value separator, preceded by `&` unless it is the
+/// first pair.
+#[inline]
+fn write_key<O, K>(output: &mut O, pairs: usize, key: &K, config: Config) -> Result<(), Error>
+where
+    O: Write,
+    K: ?Sized + Serialize,
+{
+    if pairs > 0 {
+        output.write_str("&")?;
+    }
+    key.serialize(KeySerializerNoQuotes {
+        output: PercentEncoding::new(&mut *output),
+    })?;
+    output.write_str(config.kv_separator)?;
+    Ok(())
+}
+
+/// Like [`write_key`], for a key that is already percent-encoded.
+#[inline]
+fn write_raw_key<O: Write>(
+    output: &mut O,
+    pairs: usize,
+    raw_key: &str,
+    config: Config,
+) -> Result<(), Error> {
+    if pairs > 0 {
+        output.write_str("&")?;
+    }
+    output.write_str(raw_key)?;
+    output.write_str(config.kv_separator)?;
+    Ok(())
+}
+
+/// Writes a top-level value as percent-encoded JSON, and returns its deepest
+/// array or object.
+#[inline]
+fn write_value<O, T>(output: &mut O, value: &T, config: Config) -> Result<usize, Error>
+where
+    O: Write,
+    T: ?Sized + Serialize,
+{
+    let mut output = PercentEncoding::new(output);
+    if config.force_json_string_values {
+        output.write_quote()?;
+        value.serialize(JsonSerializer {
+            output: Stringify::new(output.as_mut()),
+            is_top_level_value: true,
+            config,
+            depth: 0,
+        })?;
+        output.write_quote()?;
+    } else {
+        value.serialize(JsonSerializer {
+            output: output.as_mut(),
+            is_top_level_value: true,
+            config,
+            depth: 0,
+        })?;
+    }
+    Ok(output.max_depth())
+}
+
 #[doc(hidden)]
 pub type StructSerializer<W> = Serializer<W>;
 
@@ -440,13 +1710,42 @@ impl<W: Write> serde::ser::SerializeStruct for StructSerializer<W> {
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        serde::ser::SerializeMap::end(self)
     }
 }
 
+/// A compound value written directly after a top-level key.
+///
+/// With [`Serializer::force_json_string_values`], the value is wrapped in a
+/// JSON string instead of being written as plain JSON.
+enum TopLevelValue<J, S> {
+    Json(J),
+    Stringified(S),
+}
+
+/// The output of a top-level enum variant, held back in transactional mode.
+type VariantOutput<W> = PercentEncoding<Pending<W>>;
+
 #[doc(hidden)]
 pub struct TupleVariantSerializer<W: Write> {
-    inner: json::SeqSerializer<PercentEncoding<W>>,
+    inner: TopLevelValue<
+        json::SeqSerializer<VariantOutput<W>>,
+        json::SeqSerializer<Stringify<VariantOutput<W>>>,
+    >,
+    #[cfg(feature = "debug-verify")]
+    config: Config,
+}
+
+impl<W: Write> TupleVariantSerializer<W> {
+    /// Returns the deepest JSON array written so far, the variant's included.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn max_depth(&self) -> usize {
+        match &self.inner {
+            TopLevelValue::Json(seq) => seq.output().max_depth(),
+            TopLevelValue::Stringified(seq) => seq.output().get_ref().max_depth(),
+        }
+    }
 }
 
 impl<W: Write> serde::ser::SerializeTupleVariant for TupleVariantSerializer<W> {
@@ -460,22 +1759,53 @@ impl<W: Write> serde::ser::SerializeTupleVariant for TupleVariantSerializer<W> {
         T: ?Sized + Serialize,
     {
         use serde::ser::SerializeSeq as _;
+        #[cfg(feature = "debug-verify")]
+        verify::check(value, self.config);
         // variant=[
         // must've been written before now.
-        self.inner.serialize_element(value)
+        match &mut self.inner {
+            TopLevelValue::Json(seq) => seq.serialize_element(value),
+            TopLevelValue::Stringified(seq) => seq.serialize_element(value),
+        }
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        use serde::ser::SerializeSeq as _;
         // ]
-        self.inner.end()
+        let output = match self.inner {
+            TopLevelValue::Json(seq) => seq.finish()?,
+            TopLevelValue::Stringified(seq) => {
+                // ]"
+                let mut output = seq.finish()?.into_inner();
+                output.write_quote()?;
+                output
+            }
+        };
+        Ok(output.into_inner().commit()?)
     }
 }
 
 #[doc(hidden)]
 pub struct StructVariantSerializer<W: Write> {
-    inner: json::StructSerializer<PercentEncoding<W>>,
+    inner: TopLevelValue<
+        json::StructSerializer<VariantOutput<W>>,
+        json::StructSerializer<Stringify<VariantOutput<W>>>,
+    >,
+    #[cfg(feature = "debug-verify")]
+    config: Config,
+}
+
+impl<W: Write> StructVariantSerializer<W> {
+    /// Returns the deepest JSON object or array written so far, the variant's
+    /// included.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn max_depth(&self) -> usize {
+        match &self.inner {
+            TopLevelValue::Json(object) => object.output().max_depth(),
+            TopLevelValue::Stringified(object) => object.output().get_ref().max_depth(),
+        }
+    }
 }
 
 impl<W: Write> serde::ser::SerializeStructVariant for StructVariantSerializer<W> {
@@ -489,20 +1819,43 @@ impl<W: Write> serde::ser::SerializeStructVariant for StructVariantSerializer<W>
         T: ?Sized + serde::Serialize,
     {
         use serde::ser::SerializeStruct as _;
+        #[cfg(feature = "debug-verify")]
+        verify::check(value, self.config);
         // variant={
         // must've been written before now.
-        self.inner.serialize_field(key, value)
+        match &mut self.inner {
+            TopLevelValue::Json(object) => object.serialize_field(key, value),
+            TopLevelValue::Stringified(object) => object.serialize_field(key, value),
+        }
+    }
+
+    /// Writes nothing: a skipped field is simply absent from the object.
+    #[inline]
+    fn skip_field(&mut self, _key: &'static str) -> Result<(), Self::Error> {
+        Ok(())
     }
 
     #[inline]
     fn end(self) -> Result<Self::Ok, Self::Error> {
         // }
-        use serde::ser::SerializeStruct as _;
-
-        self.inner.end()
+        let output = match self.inner {
+            TopLevelValue::Json(object) => {
+                object.debug_assert_len();
+                object.finish()?
+            }
+            TopLevelValue::Stringified(object) => {
+                object.debug_assert_len();
+                // }"
+                let mut output = object.finish()?.into_inner();
+                output.write_quote()?;
+                output
+            }
+        };
+        Ok(output.into_inner().commit()?)
     }
 }
 
+#[cfg(feature = "std")]
 #[inline]
 #[doc(hidden)]
 pub fn percent_encoded_write<W: Write>(w: W) -> impl std::io::Write {
@@ -515,6 +1868,8 @@ pub fn internal_json_percent_encoded<W: Write>(w: W) -> impl serde::Serializer {
     JsonSerializer {
         output: PercentEncoding::new(w),
         is_top_level_value: false,
+        config: Config::default(),
+        depth: 0,
     }
 }
 
@@ -522,13 +1877,14 @@ pub fn internal_json_percent_encoded<W: Write>(w: W) -> impl serde::Serializer {
 // Tests
 //==============================================================================
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
-    use crate::error::ErrorInner;
+    use crate::error::ErrorKind;
 
     use super::*;
     use serde::Serialize;
-    use std::collections::BTreeMap;
+    use std::collections::{BTreeMap, HashMap};
+    use std::prelude::rust_2024::*;
 
     #[derive(Debug, Serialize)]
     struct SimplePayload {
@@ -573,12 +1929,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_renamed_field_keys() {
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "status[code]")]
+            status_code: u16,
+            #[serde(rename = "a b")]
+            spaced: u8,
+            plain: u8,
+        }
+
+        let value = Renamed {
+            status_code: 200,
+            spaced: 1,
+            plain: 2,
+        };
+        let expected = "status%5Bcode%5D=200&a%20b=1&plain=2";
+        // The same keys, written again, are encoded the same way.
+        assert_eq!(to_string(&value).unwrap(), expected);
+        assert_eq!(to_string(&value).unwrap(), expected);
+    }
+
     #[test]
     fn test_empty_struct() {
         #[derive(Serialize)]
-        struct Empty;
-        let result = to_string(&Empty).unwrap();
-        assert_eq!(result, "");
+        struct Empty {}
+        assert_eq!(to_string(&Empty {}).unwrap(), "");
+
+        #[derive(Serialize)]
+        struct Unit;
+        let err = to_string(&Unit).unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("UnitStruct"));
+        let err = to_string(&()).unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("()"));
+
+        // `None` has no pairs either, and always serializes to nothing.
+        assert_eq!(to_string(&Option::<Empty>::None).unwrap(), "");
+
+        // Opting in, all three are empty.
+        #[derive(Serialize)]
+        enum Kind {
+            Unit,
+        }
+        let encoder = FormEncoder::new().allow_empty_top_level(true);
+        assert_eq!(encoder.encode(&Unit).unwrap(), "");
+        assert_eq!(encoder.encode(&()).unwrap(), "");
+        assert_eq!(encoder.encode(&Kind::Unit).unwrap(), "");
     }
 
     #[test]
@@ -605,41 +2004,299 @@ mod tests {
     }
 
     #[test]
-    fn test_top_level_enum_variants() {
+    fn test_nested_non_finite_floats() {
         #[derive(Serialize)]
-        enum Status {
-            Pending,
-            Complete(u32),
-            Error { code: u32, message: String },
-            List(Vec<String>),
+        struct Outer {
+            inner: Inner,
         }
 
-        // Unit variant: ``
-        let pending = Status::Pending;
-        assert_eq!(
-            to_string(&pending).unwrap_err().inner,
-            ErrorInner::NotAnObject("UnitVariant")
-        );
+        #[derive(Serialize)]
+        struct Inner {
+            value: f64,
+        }
 
-        // Newtype variant: `Variant=<json_value>`
-        // Expected JSON: {"Complete":404}
-        let complete = Status::Complete(404);
-        assert_eq!(to_string(&complete).unwrap(), "Complete=404");
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let outer = Outer {
+                inner: Inner { value },
+            };
+            assert_eq!(to_string(&outer).unwrap(), "inner=%7B%22value%22%3Anull%7D");
+        }
 
-        // Struct variant: `Variant=<json_object>`
-        // Expected JSON: {"Error":{"code":500,"message":"Server Issue"}}
-        let error = Status::Error {
-            code: 500,
-            message: "Server Issue".to_string(),
-        };
-        let expected_error_val = "%7B%22code%22%3A500%2C%22message%22%3A%22Server%20Issue%22%7D";
-        assert_eq!(
-            to_string(&error).unwrap(),
-            format!("Error={}", expected_error_val)
-        );
+        // Deeper down, and as `f32`.
+        let deep = BTreeMap::from([("a", vec![vec![Some(f32::NAN)], vec![Some(1.5)]])]);
+        assert_eq!(to_string(&deep).unwrap(), "a=%5B%5Bnull%5D%2C%5B1.5%5D%5D");
+    }
 
-        // Tuple variant: `Variant=<json_array>`
-        // Expected JSON: {"List":["item 1","item/2"]}
+    #[test]
+    #[cfg(feature = "derive")]
+    fn test_assert_top_level_struct_accepts_objects() {
+        // Tagged enums are objects even with unit variants.
+        #[assert_top_level_struct]
+        #[derive(Serialize)]
+        #[serde(tag = "type")]
+        enum Tagged {
+            Ping,
+            Move { x: i32 },
+        }
+
+        #[assert_top_level_struct]
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Untagged {
+            Point {
+                x: i32,
+            },
+            #[serde(skip)]
+            #[allow(dead_code)]
+            Unit,
+        }
+
+        // Newtypes are only checked when serialized.
+        #[assert_top_level_struct]
+        #[derive(Serialize)]
+        struct Wrapper(BTreeMap<&'static str, u8>);
+
+        assert_eq!(to_string(&Tagged::Ping).unwrap(), "type=Ping");
+        assert_eq!(to_string(&Tagged::Move { x: 1 }).unwrap(), "type=Move&x=1");
+        assert_eq!(to_string(&Untagged::Point { x: 2 }).unwrap(), "x=2");
+        assert_eq!(
+            to_string(&Wrapper(BTreeMap::from([("a", 1)]))).unwrap(),
+            "a=1"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "derive")]
+    fn test_derive_into_form_pairs() {
+        #[derive(Serialize, IntoFormPairs)]
+        struct Request<'a, T> {
+            #[serde(rename = "access_token")]
+            token: &'a str,
+            #[serde(skip)]
+            _internal: u8,
+            body: T,
+            note: Option<&'a str>,
+        }
+
+        #[derive(Serialize, IntoFormPairs)]
+        struct Empty {}
+
+        #[derive(Serialize, IntoFormPairs)]
+        struct Raw {
+            r#type: &'static str,
+        }
+
+        let request = Request {
+            token: "a b",
+            _internal: 0,
+            body: BTreeMap::from([("k", [1.5])]),
+            note: None,
+        };
+        let keys: Vec<_> = request.into_form_pairs().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["access_token", "body", "note"]);
+        // The pairs serialize just like the struct.
+        assert_eq!(
+            to_string_from_iter(request.into_form_pairs()).unwrap(),
+            to_string(&request).unwrap()
+        );
+        assert_eq!(Empty {}.into_form_pairs().count(), 0);
+        // Raw identifiers lose their `r#`, like with serde.
+        let raw = Raw { r#type: "x" };
+        assert_eq!(
+            to_string_from_iter(raw.into_form_pairs()).unwrap(),
+            "type=x"
+        );
+        assert_eq!(to_string(&raw).unwrap(), "type=x");
+    }
+
+    #[test]
+    fn test_borrowed_fields() {
+        use std::borrow::Cow;
+
+        #[derive(Serialize)]
+        struct Request<'a> {
+            token: &'a str,
+            raw: &'a [u8],
+            #[serde(borrow)]
+            name: Cow<'a, str>,
+            #[serde(borrow)]
+            data: Cow<'a, [u8]>,
+        }
+
+        #[derive(Serialize)]
+        struct Envelope<'a> {
+            id: u32,
+            #[serde(borrow)]
+            request: Request<'a>,
+        }
+
+        let owned = String::from("Zoë & co");
+        let request = Request {
+            token: "abc123",
+            raw: b"hi",
+            name: Cow::Borrowed(&owned),
+            data: Cow::Owned(vec![0, 255]),
+        };
+        assert_eq!(
+            to_string(&request).unwrap(),
+            "token=abc123&raw=%5B104%2C105%5D&name=Zo%C3%AB%20%26%20co&data=%5B0%2C255%5D"
+        );
+
+        let envelope = Envelope { id: 7, request };
+        let encoded = to_string(&envelope).unwrap();
+        assert_eq!(
+            encoded,
+            "id=7&request=%7B%22token%22%3A%22abc123%22%2C%22raw%22%3A%5B104%2C105%5D\
+             %2C%22name%22%3A%22Zo%C3%AB%20%26%20co%22%2C%22data%22%3A%5B0%2C255%5D%7D"
+        );
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&decoded[1].1).unwrap(),
+            serde_json::to_value(&envelope.request).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_control_characters() {
+        for c in '\0'..' ' {
+            let text = format!("a{c}b");
+            let nested = BTreeMap::from([(text.as_str(), [text.as_str()])]);
+            let encoded = to_string(&BTreeMap::from([("v", &nested)])).unwrap();
+
+            // The escape is written in lowercase, and only its backslash is
+            // percent-encoded: `\u001f` becomes `%5Cu001f`.
+            let escape = serde_json::to_string(&c).unwrap();
+            let escape = &escape[1..escape.len() - 1];
+            let expected = escape.replacen('\\', "%5C", 1);
+            assert_eq!(encoded.matches(&expected).count(), 2, "{c:?}: {encoded}");
+
+            // Decoded, it's exactly what `serde_json` writes.
+            let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+            assert_eq!(decoded[0].1, serde_json::to_string(&nested).unwrap());
+
+            // Stringified, the backslash is escaped once more.
+            let encoded = FormEncoder::new()
+                .force_json_string_values(true)
+                .encode(&BTreeMap::from([("v", [text.as_str()])]))
+                .unwrap();
+            let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+            let inner: String = serde_json::from_str(&decoded[0].1).unwrap();
+            assert_eq!(inner, serde_json::to_string(&[&text]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_astral_plane_characters() {
+        #[derive(Serialize)]
+        struct Message {
+            text: &'static str,
+            emoji: char,
+            nested: BTreeMap<&'static str, (char, &'static str)>,
+        }
+
+        // An emoji and a CJK Extension B character, both outside the BMP.
+        let message = Message {
+            text: "😀𠀀",
+            emoji: '😀',
+            nested: BTreeMap::from([("𠀀", ('😀', "a😀"))]),
+        };
+        let encoded = to_string(&BTreeMap::from([("𠀀", &message)])).unwrap();
+        let top = to_string(&message).unwrap();
+        assert_eq!(
+            top,
+            "text=%F0%9F%98%80%F0%A0%80%80&emoji=%F0%9F%98%80\
+             &nested=%7B%22%F0%A0%80%80%22%3A%5B%22%F0%9F%98%80%22%2C%22a%F0%9F%98%80%22%5D%7D"
+        );
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&top).unwrap();
+        assert_eq!(decoded[0].1, "😀𠀀");
+        assert_eq!(decoded[1].1, "😀");
+        assert_eq!(
+            decoded[2].1,
+            serde_json::to_string(&message.nested).unwrap()
+        );
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+        assert_eq!(decoded[0].0, "𠀀");
+        assert_eq!(decoded[0].1, serde_json::to_string(&message).unwrap());
+
+        // Escaped as ASCII, each becomes a surrogate pair.
+        let escaped = FormEncoder::new()
+            .unicode_escape_non_ascii(true)
+            .encode(&message)
+            .unwrap();
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&escaped).unwrap();
+        assert_eq!(
+            decoded[2].1,
+            r#"{"\ud840\udc00":["\ud83d\ude00","a\ud83d\ude00"]}"#
+        );
+        let nested: BTreeMap<String, (char, String)> = serde_json::from_str(&decoded[2].1).unwrap();
+        assert_eq!(nested["𠀀"], ('😀', "a😀".to_owned()));
+
+        // `Display` output is buffered in whole pieces, so characters are never
+        // split, even when they straddle the buffer boundary.
+        struct PerChar(String);
+
+        impl std::fmt::Display for PerChar {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                use std::fmt::Write as _;
+                self.0.chars().try_for_each(|c| f.write_char(c))
+            }
+        }
+
+        impl Serialize for PerChar {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        for pad in 0..4 {
+            let text = format!("{}{}", "a".repeat(pad), "😀".repeat(40));
+            let value = BTreeMap::from([("v", [PerChar(text.clone())])]);
+            let decoded: Vec<(String, String)> =
+                serde_urlencoded::from_str(&to_string(&value).unwrap()).unwrap();
+            assert_eq!(decoded[0].1, serde_json::to_string(&[&text]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_top_level_enum_variants() {
+        #[derive(Serialize)]
+        enum Status {
+            Pending,
+            Complete(u32),
+            Error { code: u32, message: String },
+            List(Vec<String>),
+        }
+
+        // Unit variant: an error, like unit structs, unless empty values are allowed
+        let pending = Status::Pending;
+        let err = to_string(&pending).unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("UnitVariant"));
+        let mut out = String::new();
+        pending
+            .serialize(Serializer::new(&mut out).allow_empty_top_level(true))
+            .unwrap();
+        assert_eq!(out, "");
+
+        // Newtype variant: `Variant=<json_value>`
+        // Expected JSON: {"Complete":404}
+        let complete = Status::Complete(404);
+        assert_eq!(to_string(&complete).unwrap(), "Complete=404");
+
+        // Struct variant: `Variant=<json_object>`
+        // Expected JSON: {"Error":{"code":500,"message":"Server Issue"}}
+        let error = Status::Error {
+            code: 500,
+            message: "Server Issue".to_string(),
+        };
+        let expected_error_val = "%7B%22code%22%3A500%2C%22message%22%3A%22Server%20Issue%22%7D";
+        assert_eq!(
+            to_string(&error).unwrap(),
+            format!("Error={}", expected_error_val)
+        );
+
+        // Tuple variant: `Variant=<json_array>`
+        // Expected JSON: {"List":["item 1","item/2"]}
         let list = Status::List(vec!["item 1".to_string(), "item/2".to_string()]);
         let expected_list_val = "%5B%22item%201%22%2C%22item%2F2%22%5D";
         assert_eq!(
@@ -648,6 +2305,829 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_adjacently_tagged_enum() {
+        #[derive(Serialize)]
+        #[serde(tag = "type", content = "c")]
+        enum Event {
+            Ping,
+            Move(i32, i32),
+            Text(String),
+            Click { x: u32, y: u32 },
+        }
+
+        // At the top level, the tag and the content become two fields.
+        assert_eq!(to_string(&Event::Ping).unwrap(), "type=Ping");
+        assert_eq!(
+            to_string(&Event::Text("hi there".to_string())).unwrap(),
+            "type=Text&c=hi%20there"
+        );
+        // [1,-2]
+        assert_eq!(
+            to_string(&Event::Move(1, -2)).unwrap(),
+            "type=Move&c=%5B1%2C-2%5D"
+        );
+        // {"x":1,"y":2}
+        assert_eq!(
+            to_string(&Event::Click { x: 1, y: 2 }).unwrap(),
+            "type=Click&c=%7B%22x%22%3A1%2C%22y%22%3A2%7D"
+        );
+
+        #[derive(Serialize)]
+        struct Envelope {
+            id: u32,
+            event: Event,
+            events: Vec<Event>,
+        }
+
+        let envelope = Envelope {
+            id: 1,
+            event: Event::Text("a\"b".to_string()),
+            events: vec![Event::Ping, Event::Click { x: 3, y: 4 }],
+        };
+        // event: {"type":"Text","c":"a\"b"}
+        // events: [{"type":"Ping"},{"type":"Click","c":{"x":3,"y":4}}]
+        assert_eq!(
+            to_string(&envelope).unwrap(),
+            "id=1\
+             &event=%7B%22type%22%3A%22Text%22%2C%22c%22%3A%22a%5C%22b%22%7D\
+             &events=%5B%7B%22type%22%3A%22Ping%22%7D%2C\
+             %7B%22type%22%3A%22Click%22%2C%22c%22%3A%7B%22x%22%3A3%2C%22y%22%3A4%7D%7D%5D"
+        );
+    }
+
+    #[test]
+    fn test_to_string_from_iter() {
+        let pairs = vec![
+            ("name".to_string(), serde_json::json!("John Doe")),
+            ("tags".to_string(), serde_json::json!(["a", "b"])),
+            ("name".to_string(), serde_json::json!(null)),
+        ];
+        assert_eq!(
+            to_string_from_iter(pairs).unwrap(),
+            "name=John%20Doe&tags=%5B%22a%22%2C%22b%22%5D&name=null"
+        );
+
+        // Same output as going through a map.
+        let map = BTreeMap::from([("a key", 1), ("b", 2)]);
+        assert_eq!(to_string_from_iter(&map).unwrap(), to_string(&map).unwrap());
+
+        let empty: [(&str, i32); 0] = [];
+        assert_eq!(to_string_from_iter(empty).unwrap(), "");
+    }
+
+    #[test]
+    fn test_null_top_level_keys() {
+        let err = to_string(&HashMap::from([(None::<String>, 1)])).unwrap_err();
+        assert!(err.matches(ErrorKind::KeyMustBeAString));
+        assert_eq!(err.unexpected_type(), Some("Option::<T>::None"));
+        assert_eq!(
+            err.to_string(),
+            "Map key must be a string, but got Option::<T>::None"
+        );
+
+        let err = to_string(&HashMap::from([((), 1)])).unwrap_err();
+        assert!(err.matches(ErrorKind::KeyMustBeAString));
+        assert_eq!(err.unexpected_type(), Some("()"));
+    }
+
+    #[test]
+    fn test_128_bit_integer_keys() {
+        let map = std::collections::HashMap::from([(i128::MIN, "min")]);
+        assert_eq!(
+            to_string(&map).unwrap(),
+            "-170141183460469231731687303715884105728=min"
+        );
+
+        #[derive(Serialize)]
+        struct Totals {
+            by_id: std::collections::HashMap<u128, &'static str>,
+        }
+
+        let totals = Totals {
+            by_id: std::collections::HashMap::from([(u128::MAX, "max")]),
+        };
+        // {"340282366920938463463374607431768211455":"max"}
+        assert_eq!(
+            to_string(&totals).unwrap(),
+            "by_id=%7B%22340282366920938463463374607431768211455%22%3A%22max%22%7D"
+        );
+    }
+
+    #[test]
+    fn test_to_string_exact() {
+        let payload = ComplexPayload {
+            field: ComplexPayloadFieldValue {
+                recipient: "Victor & Sons".to_string(),
+                amount: 42,
+            },
+            id: 7,
+            key: Some("a \"quoted\" key".repeat(50)),
+            is_active: true,
+        };
+        let expected = to_string(&payload).unwrap();
+        assert_eq!(serialized_len(&payload).unwrap(), expected.len());
+
+        let exact = to_string_exact(&payload).unwrap();
+        assert_eq!(exact, expected);
+        // Reallocating would have left spare capacity.
+        assert_eq!(exact.capacity(), exact.len());
+
+        assert_eq!(serialized_len(&BTreeMap::<String, u8>::new()).unwrap(), 0);
+        assert!(serialized_len(&1).is_err());
+    }
+
+    #[test]
+    fn test_display_matches_to_string() {
+        fn assert_same<T: Serialize>(value: &T) {
+            assert_eq!(format!("{}", display(value)), to_string(value).unwrap());
+        }
+
+        #[derive(Serialize)]
+        struct WithOptions {
+            present: Option<&'static str>,
+            absent: Option<u32>,
+        }
+
+        #[derive(Serialize)]
+        enum Variant {
+            Newtype(Vec<u8>),
+            Tuple(i32, &'static str),
+            Struct { x: f64, label: &'static str },
+        }
+
+        assert_same(&SimplePayload {
+            id: 123,
+            name: "John Doe & \"Sons\"".to_string(),
+        });
+        assert_same(&WithOptions {
+            present: Some("a b"),
+            absent: None,
+        });
+        assert_same(&ComplexPayload {
+            field: ComplexPayloadFieldValue {
+                recipient: "Victor + Sons\n".to_string(),
+                amount: 100,
+            },
+            id: 12345,
+            key: None,
+            is_active: true,
+        });
+        assert_same(&Variant::Newtype(vec![1, 2, 3]));
+        assert_same(&Variant::Tuple(-7, "caf\u{e9}"));
+        assert_same(&Variant::Struct {
+            x: 1.5,
+            label: "{}",
+        });
+        assert_same(&BTreeMap::from([("b", vec!["x"]), ("a", vec![])]));
+    }
+
+    #[test]
+    fn test_to_string_raw() {
+        let payload = ComplexPayload {
+            field: ComplexPayloadFieldValue {
+                recipient: "Victor + Sons, 100% \"real\"".to_string(),
+                amount: 100,
+            },
+            id: 12345,
+            key: Some("café & co".to_string()),
+            is_active: true,
+        };
+        assert_eq!(
+            to_string_raw(&payload).unwrap(),
+            r#"field={"recipient":"Victor + Sons, 100% \"real\"","amount":100}&id=12345&key=café & co&is_active=true"#
+        );
+        assert!(to_string_raw(&1).is_err());
+    }
+
+    #[test]
+    fn test_output_is_ascii() {
+        #[derive(Serialize)]
+        enum Shape {
+            Circle { radius: f64 },
+        }
+
+        #[derive(Serialize)]
+        struct Everything {
+            text: &'static str,
+            control: String,
+            nested: BTreeMap<&'static str, Vec<Option<char>>>,
+            shape: Shape,
+            #[serde(with = "serde_bytes")]
+            bytes: Vec<u8>,
+            numbers: (i128, u64, f32, f64),
+        }
+
+        let value = Everything {
+            text: "caf\u{e9} \u{1F600} \"quoted\" a=b&c\\d",
+            control: (0u8..0x20).map(char::from).chain(['\u{7f}']).collect(),
+            nested: BTreeMap::from([("\u{263A} key", vec![Some('\u{10FFFF}'), None])]),
+            shape: Shape::Circle { radius: -1.5e300 },
+            bytes: (0..=255).collect(),
+            numbers: (i128::MIN, u64::MAX, f32::NAN, f64::MIN_POSITIVE),
+        };
+
+        // `to_vec` appends chunks as bytes, relying on the output being ASCII.
+        // Guard that against changes to the encode sets.
+        let bytes = to_vec(&value).unwrap();
+        assert!(bytes.is_ascii());
+        assert_eq!(bytes, to_string(&value).unwrap().into_bytes());
+
+        let mut out = String::new();
+        value
+            .serialize(Serializer::new(&mut out).force_json_string_values(true))
+            .unwrap();
+        assert!(out.is_ascii());
+    }
+
+    #[test]
+    fn test_output_is_ascii_for_any_text() {
+        fn check(text: String, nested: Vec<String>) -> bool {
+            let value = BTreeMap::from([
+                (text.clone(), serde_json::json!(text)),
+                ("nested".to_owned(), serde_json::json!(nested)),
+            ]);
+            let flat = BTreeMap::from([(text.clone(), text)]);
+            let encoders = [
+                FormEncoder::new(),
+                FormEncoder::new().force_json_string_values(true),
+                FormEncoder::new().unicode_escape_non_ascii(true),
+            ];
+            encoders
+                .iter()
+                .all(|encoder| encoder.encode(&value).unwrap().is_ascii())
+                && FormEncoder::new()
+                    .flat_strict(true)
+                    .encode(&flat)
+                    .unwrap()
+                    .is_ascii()
+                && to_vec(&value).unwrap().is_ascii()
+        }
+
+        // Generated strings mix ASCII with multi-byte UTF-8.
+        quickcheck::quickcheck(check as fn(String, Vec<String>) -> bool);
+    }
+
+    #[test]
+    fn test_to_bytes_with_capacity() {
+        let value = BTreeMap::from([("a b", vec!["c"])]);
+        let expected = to_string(&value).unwrap();
+
+        let len = serialized_len(&value).unwrap();
+        let bytes = to_bytes_with_capacity(&value, len).unwrap();
+        assert_eq!(bytes, expected.as_bytes());
+        assert_eq!(bytes.capacity(), len);
+
+        // Too small, the vector grows as usual.
+        assert_eq!(to_bytes_with_capacity(&value, 0).unwrap(), bytes);
+        assert!(to_bytes_with_capacity(&1, 16).is_err());
+    }
+
+    #[test]
+    fn test_to_slice() {
+        let payload = ComplexPayload {
+            field: ComplexPayloadFieldValue {
+                recipient: "Victor & Sons".to_string(),
+                amount: 42,
+            },
+            id: 7,
+            key: Some("caf\u{e9}".to_string()),
+            is_active: true,
+        };
+        let expected = to_string(&payload).unwrap();
+        let needed = expected.len();
+
+        // Exact fit.
+        let mut buf = vec![0u8; needed];
+        assert_eq!(to_slice(&mut buf, &payload).unwrap(), needed);
+        assert_eq!(buf, expected.as_bytes());
+
+        // Room to spare.
+        let mut buf = vec![0u8; needed + 10];
+        assert_eq!(to_slice(&mut buf, &payload).unwrap(), needed);
+        assert_eq!(&buf[..needed], expected.as_bytes());
+
+        // One byte too small.
+        let mut buf = vec![0u8; needed - 1];
+        let err = to_slice(&mut buf, &payload).unwrap_err();
+        assert!(err.matches(ErrorKind::BufferTooSmall));
+        assert_eq!(err.needed_len(), Some(needed));
+
+        assert_eq!(to_slice(&mut [], &BTreeMap::<u8, u8>::new()).unwrap(), 0);
+        assert_eq!(to_slice(&mut [], &1).unwrap_err().needed_len(), None);
+    }
+
+    #[test]
+    fn test_struct_variant_skipped_fields() {
+        #[derive(Serialize)]
+        enum Event {
+            Update {
+                id: u32,
+                #[serde(skip)]
+                _internal: u32,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                note: Option<&'static str>,
+                last: bool,
+            },
+        }
+
+        let event = Event::Update {
+            id: 1,
+            _internal: 99,
+            note: None,
+            last: true,
+        };
+        // {"id":1,"last":true}
+        assert_eq!(
+            to_string(&event).unwrap(),
+            "Update=%7B%22id%22%3A1%2C%22last%22%3Atrue%7D"
+        );
+
+        let mut out = String::new();
+        event
+            .serialize(Serializer::new(&mut out).force_json_string_values(true))
+            .unwrap();
+        // "{\"id\":1,\"last\":true}"
+        assert_eq!(
+            out,
+            "Update=%22%7B%5C%22id%5C%22%3A1%2C%5C%22last%5C%22%3Atrue%7D%22"
+        );
+    }
+
+    #[test]
+    fn test_with_writer() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        // Start a payload, then move on to a new writer.
+        let mut first = String::new();
+        let mut map = Serializer::new(&mut first)
+            .force_json_string_values(true)
+            .serialize_map(None)
+            .unwrap();
+        map.serialize_entry("a", &1).unwrap();
+
+        let mut second = String::new();
+        BTreeMap::from([("b", 2), ("c", 3)])
+            .serialize(map.with_writer(&mut second))
+            .unwrap();
+        assert_eq!(first, "a=%221%22");
+        // A fresh payload: no leading `&`, same options.
+        assert_eq!(second, "b=%222%22&c=%223%22");
+    }
+
+    #[test]
+    fn test_flatten() {
+        #[derive(Serialize)]
+        struct Common {
+            access_token: &'static str,
+            version: u32,
+            limits: BTreeMap<&'static str, i32>,
+        }
+
+        #[derive(Serialize)]
+        struct Flattened {
+            id: u64,
+            #[serde(flatten)]
+            common: Common,
+            tags: Vec<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Inline {
+            id: u64,
+            access_token: &'static str,
+            version: u32,
+            limits: BTreeMap<&'static str, i32>,
+            tags: Vec<&'static str>,
+        }
+
+        let common = || Common {
+            access_token: "t k",
+            version: 2,
+            limits: BTreeMap::from([("calls", 200)]),
+        };
+        let flattened = Flattened {
+            id: 1,
+            common: common(),
+            tags: vec!["a"],
+        };
+        let inline = Inline {
+            id: 1,
+            access_token: "t k",
+            version: 2,
+            limits: BTreeMap::from([("calls", 200)]),
+            tags: vec!["a"],
+        };
+        assert_eq!(to_string(&flattened).unwrap(), to_string(&inline).unwrap());
+        let forced = FormEncoder::new().force_json_string_values(true);
+        assert_eq!(
+            forced.encode(&flattened).unwrap(),
+            forced.encode(&inline).unwrap()
+        );
+
+        // Nested inside a value, flattening goes through the JSON serializer.
+        assert_eq!(
+            to_string(&BTreeMap::from([("batch", &flattened)])).unwrap(),
+            to_string(&BTreeMap::from([("batch", &inline)])).unwrap()
+        );
+
+        #[derive(Serialize)]
+        struct MaybeFlattened {
+            id: u64,
+            #[serde(flatten)]
+            common: Option<Common>,
+        }
+
+        assert_eq!(
+            to_string(&MaybeFlattened {
+                id: 1,
+                common: Some(common()),
+            })
+            .unwrap(),
+            "id=1&access_token=t%20k&version=2&limits=%7B%22calls%22%3A200%7D"
+        );
+        assert_eq!(
+            to_string(&MaybeFlattened {
+                id: 1,
+                common: None,
+            })
+            .unwrap(),
+            "id=1"
+        );
+
+        #[derive(Serialize)]
+        struct Extra {
+            id: u64,
+            #[serde(flatten)]
+            extra: HashMap<&'static str, serde_json::Value>,
+        }
+
+        let extra = Extra {
+            id: 1,
+            extra: HashMap::from([("filter", serde_json::json!({"n": 5, "s": "v"}))]),
+        };
+        assert_eq!(
+            to_string(&extra).unwrap(),
+            "id=1&filter=%7B%22n%22%3A5%2C%22s%22%3A%22v%22%7D"
+        );
+    }
+
+    #[test]
+    fn test_flatten_flat_strict() {
+        #[derive(Serialize)]
+        struct Paging {
+            limit: u32,
+            after: Option<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Search {
+            q: &'static str,
+            #[serde(flatten)]
+            paging: Paging,
+        }
+
+        let search = Search {
+            q: "rust serde",
+            paging: Paging {
+                limit: 10,
+                after: None,
+            },
+        };
+        assert_eq!(
+            FormEncoder::new()
+                .flat_strict(true)
+                .encode(&search)
+                .unwrap(),
+            serde_urlencoded::to_string(&search).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_append_to_string() {
+        #[derive(Serialize)]
+        struct Empty {}
+
+        let mut body = String::new();
+        append_to_string(&mut body, &Empty {}).unwrap();
+        assert_eq!(body, "");
+        append_to_string(&mut body, &BTreeMap::from([("a", 1)])).unwrap();
+        assert_eq!(body, "a=1");
+        append_to_string(&mut body, &Empty {}).unwrap();
+        assert_eq!(body, "a=1");
+        append_to_string(&mut body, &BTreeMap::from([("b", 2), ("c", 3)])).unwrap();
+        assert_eq!(body, "a=1&b=2&c=3");
+
+        // A failed append leaves the payload as it was, even after some of
+        // its pairs were written.
+        #[derive(Serialize)]
+        struct Partial {
+            d: u8,
+            e: BTreeMap<(), u8>,
+        }
+        let partial = Partial {
+            d: 4,
+            e: BTreeMap::from([((), 5)]),
+        };
+        assert!(append_to_string(&mut body, &partial).is_err());
+        assert_eq!(body, "a=1&b=2&c=3");
+    }
+
+    #[test]
+    fn test_unicode_escape_non_ascii() {
+        #[derive(Serialize)]
+        struct Message {
+            from: &'static str,
+            body: BTreeMap<&'static str, &'static str>,
+            reactions: Vec<char>,
+            when: Option<Uuid>,
+        }
+
+        /// Serialized with `collect_str`.
+        struct Uuid;
+
+        impl Serialize for Uuid {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str("ünïcode")
+            }
+        }
+
+        let message = Message {
+            from: "Zoë",
+            body: BTreeMap::from([("téxt", "a\"é\n")]),
+            reactions: vec!['😀', 'a'],
+            when: Some(Uuid),
+        };
+        let mut out = String::new();
+        message
+            .serialize(Serializer::new(&mut out).unicode_escape_non_ascii(true))
+            .unwrap();
+        assert_eq!(
+            out,
+            "from=Zo%C3%AB\
+             &body=%7B%22t%5Cu00e9xt%22%3A%22a%5C%22%5Cu00e9%5Cn%22%7D\
+             &reactions=%5B%22%5Cud83d%5Cude00%22%2C%22a%22%5D\
+             &when=%C3%BCn%C3%AFcode"
+        );
+
+        // The escapes decode to the JSON `serde_json` reads back.
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&out).unwrap();
+        let body: BTreeMap<String, String> = serde_json::from_str(&decoded[1].1).unwrap();
+        assert_eq!(body["téxt"], "a\"é\n");
+        let reactions: Vec<char> = serde_json::from_str(&decoded[2].1).unwrap();
+        assert_eq!(reactions, ['😀', 'a']);
+
+        // Stringified, the backslash of each escape is escaped in turn.
+        let encoded = FormEncoder::new()
+            .unicode_escape_non_ascii(true)
+            .force_json_string_values(true)
+            .encode(&BTreeMap::from([("v", ["é"])]))
+            .unwrap();
+        assert_eq!(encoded, "v=%22%5B%5C%22%5C%5Cu00e9%5C%22%5D%22");
+    }
+
+    #[test]
+    fn test_transactional() {
+        #[derive(Serialize)]
+        struct Upload {
+            id: u32,
+            name: &'static str,
+            data: Vec<Failing>,
+            after: u32,
+        }
+
+        #[derive(Serialize)]
+        enum Event {
+            Moved { to: Vec<Failing> },
+            Resized(u32, Failing),
+        }
+
+        /// Fails after part of the JSON value has been written.
+        struct Failing;
+
+        impl Serialize for Failing {
+            fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("boom"))
+            }
+        }
+
+        let upload = Upload {
+            id: 1,
+            name: "a b",
+            data: vec![Failing],
+            after: 2,
+        };
+
+        // Streamed, the failed pair leaves a fragment behind.
+        let mut out = String::new();
+        assert!(upload.serialize(Serializer::new(&mut out)).is_err());
+        assert_eq!(out, "id=1&name=a%20b&data=%5B");
+
+        // Transactional, only the first two pairs are written.
+        let mut out = String::new();
+        let ser = Serializer::new(&mut out).transactional(true);
+        assert!(upload.serialize(ser).is_err());
+        assert_eq!(out, "id=1&name=a%20b");
+
+        let mut out = String::new();
+        let ser = Serializer::new(&mut out)
+            .transactional(true)
+            .force_json_string_values(true);
+        assert!(upload.serialize(ser).is_err());
+        assert_eq!(out, "id=%221%22&name=%22a%20b%22");
+
+        // Top-level enum variants are held back too.
+        for event in [
+            Event::Moved { to: vec![Failing] },
+            Event::Resized(3, Failing),
+        ] {
+            let mut out = String::from("keep");
+            let ser = Serializer::new(&mut out).transactional(true);
+            assert!(event.serialize(ser).is_err());
+            assert_eq!(out, "keep");
+        }
+
+        // The encoder is transactional by default.
+        let mut out = String::new();
+        assert!(
+            FormEncoder::new()
+                .encode_to_writer(&mut out, &upload)
+                .is_err()
+        );
+        assert_eq!(out, "id=1&name=a%20b");
+
+        // Complete pairs are written as usual.
+        let mut out = String::new();
+        let ser = Serializer::new(&mut out).transactional(true);
+        BTreeMap::from([("a", vec![1, 2]), ("b", vec![])])
+            .serialize(ser)
+            .unwrap();
+        assert_eq!(out, "a=%5B1%2C2%5D&b=%5B%5D");
+    }
+
+    #[test]
+    fn test_poisoned_after_failed_pair() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+        // A nested map with a key that isn't a string fails after `{`.
+        let bad = BTreeMap::from([(vec![1], 1)]);
+        map.serialize_entry("a", &1).unwrap();
+        let err = map.serialize_entry("b", &bad).unwrap_err();
+        assert!(err.matches(ErrorKind::KeyMustBeAString));
+
+        let err = map.serialize_entry("c", &2).unwrap_err();
+        assert!(err.matches(ErrorKind::Poisoned));
+        assert!(
+            map.serialize_key("c")
+                .unwrap_err()
+                .matches(ErrorKind::Poisoned)
+        );
+        assert!(
+            map.serialize_value(&2)
+                .unwrap_err()
+                .matches(ErrorKind::Poisoned)
+        );
+
+        // The fragment is left for the caller to deal with.
+        map.reset();
+        map.serialize_entry("c", &2).unwrap();
+        assert_eq!(map.pairs_written(), 1);
+        assert_eq!(out, "a=1&b=%7B%22c=2");
+
+        // With a fresh writer, the payload is whole.
+        let mut map = Serializer::new(String::new()).serialize_map(None).unwrap();
+        assert!(map.serialize_entry("b", &bad).is_err());
+        let mut map = map.with_writer(String::new());
+        map.serialize_entry("c", &2).unwrap();
+        assert_eq!(map.into_inner(), "c=2");
+
+        // Transactional mode leaves no fragment, so it carries on.
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out)
+            .transactional(true)
+            .serialize_map(None)
+            .unwrap();
+        assert!(map.serialize_entry("b", &bad).is_err());
+        map.serialize_entry("c", &2).unwrap();
+        assert_eq!(out, "c=2");
+    }
+
+    #[test]
+    fn test_serialize_raw_pair() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out)
+            .kv_separator(":")
+            .serialize_map(None)
+            .unwrap();
+        map.serialize_raw_pair("a%20b", &[1, 2]).unwrap();
+        map.serialize_entry("c d", &"e").unwrap();
+        map.serialize_raw_pair("f%5B0%5D", &None::<u8>).unwrap();
+        assert_eq!(map.pairs_written(), 3);
+        assert_eq!(out, "a%20b:%5B1%2C2%5D&c%20d:e&f%5B0%5D:null");
+
+        // A failed value leaves nothing behind in transactional mode.
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out)
+            .transactional(true)
+            .serialize_map(None)
+            .unwrap();
+        assert!(
+            map.serialize_raw_pair("bad", &BTreeMap::from([(vec![1], 1)]))
+                .is_err()
+        );
+        map.serialize_raw_pair("ok", &1).unwrap();
+        assert_eq!(out, "ok=1");
+
+        // In flat mode, `None` still leaves the pair out.
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out)
+            .flat_strict(true)
+            .serialize_map(None)
+            .unwrap();
+        map.serialize_raw_pair("a+b", &None::<u8>).unwrap();
+        map.serialize_raw_pair("a+b", &"c d").unwrap();
+        assert_eq!(out, "a+b=c+d");
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "raw key is not percent-encoded")]
+    fn test_serialize_raw_pair_checks_key() {
+        use serde::ser::Serializer as _;
+
+        let mut map = Serializer::new(String::new()).serialize_map(None).unwrap();
+        let _ = map.serialize_raw_pair("a=b", &1);
+    }
+
+    #[test]
+    fn test_end_non_empty() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        let map = Serializer::new(String::new()).serialize_map(None).unwrap();
+        let err = map.end_non_empty().unwrap_err();
+        assert_eq!(err.message(), Some("form body must not be empty"));
+
+        // Pairs that are left out don't count.
+        let mut map = Serializer::new(String::new())
+            .flat_strict(true)
+            .serialize_map(None)
+            .unwrap();
+        map.serialize_entry("a", &None::<u8>).unwrap();
+        assert!(map.end_non_empty().is_err());
+
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+        map.serialize_entry("a", &None::<u8>).unwrap();
+        map.end_non_empty().unwrap();
+        assert_eq!(out, "a=null");
+    }
+
+    #[test]
+    fn test_pairs_written() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out).serialize_map(None).unwrap();
+        assert_eq!(map.pairs_written(), 0);
+        map.serialize_entry("a", &None::<u8>).unwrap();
+        map.serialize_entry("b", &[1, 2]).unwrap();
+        assert_eq!(map.pairs_written(), 2);
+
+        // Omitted pairs are not counted.
+        let mut out = String::new();
+        let mut map = Serializer::new(&mut out)
+            .flat_strict(true)
+            .serialize_map(None)
+            .unwrap();
+        map.serialize_entry("a", &None::<u8>).unwrap();
+        map.serialize_entry("b", &1).unwrap();
+        assert_eq!(map.pairs_written(), 1);
+        assert_eq!(out, "b=1");
+    }
+
+    #[test]
+    fn test_writer_error_source() {
+        use std::error::Error as _;
+
+        struct Failing;
+
+        impl Write for Failing {
+            fn write_str(&mut self, _: &str) -> std::fmt::Result {
+                Err(std::fmt::Error)
+            }
+        }
+
+        let err = to_writer(Failing, &BTreeMap::from([("a", 1)])).unwrap_err();
+        assert!(err.matches(ErrorKind::Fmt));
+        assert!(err.source().unwrap().is::<std::fmt::Error>());
+
+        assert!(to_string(&1).unwrap_err().source().is_none());
+    }
+
     #[test]
     fn test_skip_none_field() {
         let payload = ComplexPayload {
@@ -665,15 +3145,383 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_force_json_string_values() {
+        fn to_string_forced<T: Serialize>(value: &T) -> String {
+            let mut out = String::new();
+            value
+                .serialize(Serializer::new(&mut out).force_json_string_values(true))
+                .unwrap();
+            out
+        }
+
+        #[derive(Serialize)]
+        struct Inner {
+            name: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Payload {
+            count: u32,
+            ratio: f64,
+            active: bool,
+            missing: Option<u32>,
+            text: &'static str,
+            inner: Inner,
+            list: Vec<&'static str>,
+        }
+
+        let payload = Payload {
+            count: 1,
+            ratio: 0.5,
+            active: false,
+            missing: None,
+            text: "say \"hi\"",
+            inner: Inner { name: "a b" },
+            list: vec!["x"],
+        };
+        // "1", "0.5", "false", "null", "say \"hi\"",
+        // "{\"name\":\"a b\"}", "[\"x\"]"
+        assert_eq!(
+            to_string_forced(&payload),
+            "count=%221%22&ratio=%220.5%22&active=%22false%22&missing=%22null%22\
+             &text=%22say%20%5C%22hi%5C%22%22\
+             &inner=%22%7B%5C%22name%5C%22%3A%5C%22a%20b%5C%22%7D%22\
+             &list=%22%5B%5C%22x%5C%22%5D%22"
+        );
+
+        #[derive(Serialize)]
+        enum Status {
+            Complete(u32),
+            List(u32, &'static str),
+            Error { code: u32 },
+        }
+
+        assert_eq!(
+            to_string_forced(&Status::Complete(404)),
+            "Complete=%22404%22"
+        );
+        // "[1,\"a\"]"
+        assert_eq!(
+            to_string_forced(&Status::List(1, "a")),
+            "List=%22%5B1%2C%5C%22a%5C%22%5D%22"
+        );
+        // "{\"code\":500}"
+        assert_eq!(
+            to_string_forced(&Status::Error { code: 500 }),
+            "Error=%22%7B%5C%22code%5C%22%3A500%7D%22"
+        );
+    }
+
+    fn to_string_flat<T: Serialize>(value: &T) -> Result<String, Error> {
+        let mut out = String::new();
+        value.serialize(Serializer::new(&mut out).flat_strict(true))?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_bool_as_int() {
+        fn to_string_bool_as_int<T: Serialize>(value: &T) -> Result<String, Error> {
+            let mut out = String::new();
+            value.serialize(Serializer::new(&mut out).bool_as_int(true))?;
+            Ok(out)
+        }
+
+        #[derive(Serialize)]
+        struct Settings {
+            enabled: bool,
+            flags: BTreeMap<bool, bool>,
+            nested: Option<Vec<(bool, u8)>>,
+        }
+
+        let settings = Settings {
+            enabled: false,
+            flags: BTreeMap::from([(true, false)]),
+            nested: Some(vec![(true, 1)]),
+        };
+        // flags: {"true":0}, keys are unaffected.
+        // nested: [[1,1]]
+        assert_eq!(
+            to_string_bool_as_int(&settings).unwrap(),
+            "enabled=0&flags=%7B%22true%22%3A0%7D&nested=%5B%5B1%2C1%5D%5D"
+        );
+        // Top-level keys are unaffected too.
+        assert_eq!(
+            to_string_bool_as_int(&BTreeMap::from([(true, true)])).unwrap(),
+            "true=1"
+        );
+
+        // Combined with string values: "1"
+        let mut out = String::new();
+        BTreeMap::from([("on", true)])
+            .serialize(
+                Serializer::new(&mut out)
+                    .bool_as_int(true)
+                    .force_json_string_values(true),
+            )
+            .unwrap();
+        assert_eq!(out, "on=%221%22");
+    }
+
+    #[test]
+    fn test_kv_separator() {
+        fn to_string_with_separator<T: Serialize>(
+            value: &T,
+            separator: &'static str,
+        ) -> Result<String, Error> {
+            let mut out = String::new();
+            value.serialize(Serializer::new(&mut out).kv_separator(separator))?;
+            Ok(out)
+        }
+
+        let value = BTreeMap::from([("a b", "c=d"), ("list", "[1]")]);
+        assert_eq!(
+            to_string_with_separator(&value, ":").unwrap(),
+            "a%20b:c%3Dd&list:%5B1%5D"
+        );
+        // Written verbatim.
+        assert_eq!(
+            to_string_with_separator(&value, " ").unwrap(),
+            "a%20b c%3Dd&list %5B1%5D"
+        );
+
+        let mut out = String::new();
+        value
+            .serialize(
+                Serializer::new(&mut out)
+                    .kv_separator(":")
+                    .flat_strict(true),
+            )
+            .unwrap();
+        assert_eq!(out, "a+b:c%3Dd&list:%5B1%5D");
+    }
+
+    #[test]
+    #[should_panic(expected = "must not contain `&`")]
+    fn test_kv_separator_rejects_ampersand() {
+        let _ = Serializer::new(String::new()).kv_separator("=&");
+    }
+
+    #[test]
+    #[should_panic(expected = "must be ASCII")]
+    fn test_kv_separator_rejects_non_ascii() {
+        let _ = FormEncoder::new().kv_separator("\u{2192}");
+    }
+
+    #[test]
+    fn test_flat_strict_matches_serde_urlencoded() {
+        #[derive(Serialize)]
+        enum Kind {
+            Basic,
+        }
+
+        #[derive(Serialize)]
+        struct Unit;
+
+        #[derive(Serialize)]
+        struct Id(u64);
+
+        #[derive(Serialize)]
+        struct Flat {
+            text: &'static str,
+            symbols: &'static str,
+            unicode: String,
+            int: i64,
+            big: u128,
+            float: f64,
+            whole: f32,
+            nan: f64,
+            yes: bool,
+            letter: char,
+            some: Option<&'static str>,
+            none: Option<u32>,
+            kind: Kind,
+            unit: Unit,
+            id: Id,
+            #[serde(rename = "renamed key")]
+            renamed: &'static str,
+        }
+
+        let flat = Flat {
+            text: "hello world",
+            symbols: "a+b=c&d*e~f-g.h_i/j?k#l%m\"n'",
+            unicode: "héllo 😀".to_string(),
+            int: -42,
+            big: u128::MAX,
+            float: 3.25,
+            whole: 1.0,
+            nan: f64::NAN,
+            yes: true,
+            letter: ' ',
+            some: Some("x y"),
+            none: None,
+            kind: Kind::Basic,
+            unit: Unit,
+            id: Id(7),
+            renamed: "",
+        };
+        assert_eq!(
+            to_string_flat(&flat).unwrap(),
+            serde_urlencoded::to_string(&flat).unwrap()
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert("key w/ spaces", "value w/ spaces");
+        map.insert("another~key", "a=b&c=d");
+        assert_eq!(
+            to_string_flat(&map).unwrap(),
+            serde_urlencoded::to_string(&map).unwrap()
+        );
+
+        // Leading `None`s must not leave a stray separator.
+        #[derive(Serialize)]
+        struct Sparse {
+            a: Option<u32>,
+            b: Option<u32>,
+            c: u32,
+        }
+        let sparse = Sparse {
+            a: None,
+            b: None,
+            c: 1,
+        };
+        assert_eq!(to_string_flat(&sparse).unwrap(), "c=1");
+        assert_eq!(
+            to_string_flat(&sparse).unwrap(),
+            serde_urlencoded::to_string(&sparse).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_flat_strict_rejects_nested_values() {
+        let nested = ComplexPayload {
+            field: ComplexPayloadFieldValue {
+                recipient: "test".to_string(),
+                amount: 50,
+            },
+            id: 99,
+            key: None,
+            is_active: false,
+        };
+        let err = to_string_flat(&nested).unwrap_err();
+        assert!(err.matches(ErrorKind::NestedValueInFlatMode));
+        assert_eq!(err.key(), Some("field"));
+
+        let mut map = BTreeMap::new();
+        map.insert("list", vec![1, 2]);
+        let err = to_string_flat(&map).unwrap_err();
+        assert!(err.matches(ErrorKind::NestedValueInFlatMode));
+        assert_eq!(err.key(), Some("list"));
+
+        #[derive(Serialize)]
+        enum Status {
+            Error { code: u32 },
+        }
+        let err = to_string_flat(&Status::Error { code: 1 }).unwrap_err();
+        assert!(err.matches(ErrorKind::NestedValueInFlatMode));
+        assert_eq!(err.key(), Some("Error"));
+    }
+
+    #[test]
+    fn test_encode_rfc3986_query() {
+        // Everything a query may contain is left alone.
+        let allowed = "AZaz09-._~!$&'()*+,;=:@/?";
+        assert_eq!(encode_rfc3986_query(allowed), allowed);
+
+        assert_eq!(encode_rfc3986_query(""), "");
+        assert_eq!(
+            encode_rfc3986_query("a b#c%d\"e<f>g[h]i{j}k|l\\m^n`o"),
+            "a%20b%23c%25d%22e%3Cf%3Eg%5Bh%5Di%7Bj%7Dk%7Cl%5Cm%5En%60o"
+        );
+        assert_eq!(encode_rfc3986_query("é"), "%C3%A9");
+    }
+
+    #[test]
+    fn test_encode_rfc5987() {
+        // Every `attr-char` is left alone.
+        let allowed = "AZaz09!#$&+-.^_`|~";
+        assert_eq!(encode_rfc5987(allowed), allowed);
+
+        assert_eq!(encode_rfc5987(""), "");
+        assert_eq!(
+            encode_rfc5987("a b%c'd*e(f)g,h;i=j:k@l/m?n\"o[p]q{r}s\\t"),
+            "a%20b%25c%27d%2Ae%28f%29g%2Ch%3Bi%3Dj%3Ak%40l%2Fm%3Fn%22o%5Bp%5Dq%7Br%7Ds%5Ct"
+        );
+        assert_eq!(encode_rfc5987("€ rates"), "%E2%82%AC%20rates");
+    }
+
+    #[test]
+    fn test_serde_bytes_field() {
+        #[derive(Serialize)]
+        struct Upload<'a> {
+            name: &'a str,
+            #[serde(with = "serde_bytes")]
+            data: &'a [u8],
+            #[serde(with = "serde_bytes")]
+            owned: Vec<u8>,
+        }
+
+        let upload = Upload {
+            name: "file",
+            data: b"hi",
+            owned: vec![],
+        };
+        // Same output as a plain `Vec<u8>`: a JSON array of integers.
+        assert_eq!(
+            to_string(&upload).unwrap(),
+            "name=file&data=%5B104%2C105%5D&owned=%5B%5D"
+        );
+    }
+
+    #[test]
+    fn test_serde_with_display_from_str() {
+        use serde_with::{DisplayFromStr, serde_as};
+
+        #[serde_as]
+        #[derive(Serialize)]
+        struct Amount {
+            #[serde_as(as = "DisplayFromStr")]
+            value: u64,
+        }
+
+        #[serde_as]
+        #[derive(Serialize)]
+        struct Transfer {
+            #[serde_as(as = "DisplayFromStr")]
+            id: u64,
+            amount: Amount,
+            #[serde_as(as = "Vec<DisplayFromStr>")]
+            refs: Vec<u32>,
+        }
+
+        let transfer = Transfer {
+            id: 42,
+            amount: Amount { value: 1500 },
+            refs: vec![7, 8],
+        };
+        // Nested, the display output is a JSON string. At the top level it is
+        // written bare, like every other string.
+        assert_eq!(
+            to_string(&transfer).unwrap(),
+            "id=42&amount=%7B%22value%22%3A%221500%22%7D&refs=%5B%227%22%2C%228%22%5D"
+        );
+        assert_eq!(
+            to_string(&Amount { value: 1500 }).unwrap(),
+            to_string(&BTreeMap::from([("value", "1500")])).unwrap()
+        );
+    }
+
     #[test]
     fn test_top_level_errors() {
         // Top-level primitives must fail
         let err_int = to_string(&123).unwrap_err();
-        assert_eq!(err_int.inner, ErrorInner::NotAnObject("i32"));
+        assert!(err_int.matches(ErrorKind::NotAnObject));
+        assert_eq!(err_int.unexpected_type(), Some("i32"));
 
         // Top-level sequences must fail
         let err_seq = to_string(&vec![1, 2, 3]).unwrap_err();
-        assert_eq!(err_seq.inner, ErrorInner::NotAnObject("Seq"));
+        assert!(err_seq.matches(ErrorKind::NotAnObject));
+        assert_eq!(err_seq.unexpected_type(), Some("Seq"));
 
         // Top-level enums (that aren't structs/maps) must fail
         #[derive(Serialize)]
@@ -681,26 +3529,58 @@ mod tests {
             Unit,
         }
         let err_enum = to_string(&MyEnum::Unit).unwrap_err();
-        assert_eq!(err_enum.inner, ErrorInner::NotAnObject("UnitVariant"));
+        assert!(err_enum.matches(ErrorKind::NotAnObject));
+        assert_eq!(err_enum.unexpected_type(), Some("UnitVariant"));
 
         // Primitives must fail
-        assert_eq!(
-            to_string(&123).unwrap_err().inner,
-            ErrorInner::NotAnObject("i32")
-        );
-        assert_eq!(
-            to_string("a str").unwrap_err().inner,
-            ErrorInner::NotAnObject("str")
-        );
+        let err = to_string(&123).unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("i32"));
+        let err = to_string("a str").unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("str"));
 
         // Sequences must fail
-        assert_eq!(
-            to_string(&vec![1, 2]).unwrap_err().inner,
-            ErrorInner::NotAnObject("Seq")
-        );
-        assert_eq!(
-            to_string(&(1, 4)).unwrap_err().inner,
-            ErrorInner::NotAnObject("Tuple")
-        );
+        let err = to_string(&vec![1, 2]).unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("Seq"));
+        let err = to_string(&(1, 4)).unwrap_err();
+        assert!(err.matches(ErrorKind::NotAnObject));
+        assert_eq!(err.unexpected_type(), Some("Tuple"));
+    }
+
+    #[test]
+    fn test_top_level_primitive_errors() {
+        fn not_an_object<T: Serialize>(value: T) -> &'static str {
+            let err = to_string(&value).unwrap_err();
+            assert!(
+                err.matches(ErrorKind::NotAnObject),
+                "unexpected error: {err:?}"
+            );
+            err.unexpected_type().unwrap()
+        }
+
+        assert_eq!(not_an_object(true), "bool");
+        assert_eq!(not_an_object(1i8), "i8");
+        assert_eq!(not_an_object(1i16), "i16");
+        assert_eq!(not_an_object(1i32), "i32");
+        assert_eq!(not_an_object(1i64), "i64");
+        assert_eq!(not_an_object(1i128), "i128");
+        assert_eq!(not_an_object(1u8), "u8");
+        assert_eq!(not_an_object(1u16), "u16");
+        assert_eq!(not_an_object(1u32), "u32");
+        assert_eq!(not_an_object(1u64), "u64");
+        assert_eq!(not_an_object(1u128), "u128");
+        assert_eq!(not_an_object(1.5f32), "f32");
+        assert_eq!(not_an_object(1.5f64), "f64");
+        assert_eq!(not_an_object('c'), "char");
+        assert_eq!(not_an_object("s"), "str");
+        assert_eq!(not_an_object(serde_bytes::Bytes::new(b"b")), "bytes");
+        assert_eq!(not_an_object(format_args!("{}", 1)), "str", "collect_str");
+
+        // Also through a newtype wrapper.
+        #[derive(Serialize)]
+        struct Id(u128);
+        assert_eq!(not_an_object(Id(1)), "u128");
     }
 }
@@ -0,0 +1,51 @@
+//! Listing a struct's fields as key-value pairs.
+//!
+//! This module is only available with the `derive` feature enabled.
+
+/// A struct whose fields can be listed as `(key, value)` pairs.
+///
+/// Derive it with `#[derive(IntoFormPairs)]`. The pairs are the ones `serde`
+/// would write, in the same order, but come straight from the fields instead of
+/// through a [`Serializer`](crate::Serializer). Pass them to
+/// [`to_string_from_iter`](crate::to_string_from_iter), or pick out single
+/// fields by key.
+///
+/// The derive honors `#[serde(rename = "...")]` and `#[serde(skip)]`, and
+/// rejects attributes like `flatten`, `skip_serializing_if` and
+/// `into = "..."` whose output can't be listed field by field.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_metaform::IntoFormPairs;
+///
+/// #[derive(IntoFormPairs)]
+/// struct Message {
+///     #[serde(rename = "messaging_product")]
+///     product: &'static str,
+///     to: u64,
+///     tags: Vec<&'static str>,
+/// }
+///
+/// let message = Message { product: "whatsapp", to: 1555, tags: vec!["a"] };
+/// let keys: Vec<_> = message.into_form_pairs().map(|(key, _)| key).collect();
+/// assert_eq!(keys, ["messaging_product", "to", "tags"]);
+///
+/// let encoded = serde_metaform::to_string_from_iter(message.into_form_pairs()).unwrap();
+/// assert_eq!(encoded, "messaging_product=whatsapp&to=1555&tags=%5B%22a%22%5D");
+/// ```
+///
+/// ```rust,compile_fail
+/// use serde_metaform::IntoFormPairs;
+///
+/// #[derive(Clone, IntoFormPairs)]
+/// #[serde(into = "String")] // error: serialized as a `String`, not field by field
+/// struct Id { value: u64 }
+/// ```
+pub trait IntoFormPairs {
+    /// Returns the fields as `(key, value)` pairs, in declaration order.
+    // Borrows, so the pairs can be listed again, e.g. after a failed request.
+    #[allow(clippy::wrong_self_convention)]
+    fn into_form_pairs(&self)
+    -> impl Iterator<Item = (&'static str, &dyn erased_serde::Serialize)>;
+}
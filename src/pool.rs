@@ -0,0 +1,181 @@
+//! Thread-local pooled output buffers.
+//!
+//! This module is only available with the `pool` feature enabled. It provides
+//! [`to_pooled_string`], which serializes into a buffer taken from a small
+//! per-thread pool instead of a freshly allocated `String`. The buffer goes
+//! back to the pool when the returned [`PooledString`] is dropped, so a thread
+//! that serializes one payload at a time allocates only while its buffers are
+//! still growing.
+
+use std::{cell::RefCell, fmt, ops::Deref};
+
+use serde::Serialize;
+
+use crate::{error::Error, to_writer};
+
+/// The most buffers kept per thread.
+const MAX_POOLED_BUFFERS: usize = 4;
+
+/// The largest buffer capacity, in bytes, that is returned to the pool.
+///
+/// Larger buffers are freed on drop, so one huge payload doesn't pin its
+/// memory for the lifetime of the thread.
+pub const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+
+thread_local! {
+    static POOL: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Serializes the given data structure into a pooled `String`.
+///
+/// The output is the same as [`to_string`](crate::to_string)'s.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::pool::to_pooled_string;
+///
+/// #[derive(Serialize)]
+/// struct Point { x: i32, y: i32 }
+///
+/// let body = to_pooled_string(&Point { x: 1, y: 2 }).unwrap();
+/// assert_eq!(&*body, "x=1&y=2");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`] for details.
+pub fn to_pooled_string<T>(value: &T) -> Result<PooledString, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut pooled = PooledString { buf: take() };
+    to_writer(&mut pooled.buf, value)?;
+    Ok(pooled)
+}
+
+/// A serialized payload whose buffer returns to the thread-local pool on drop.
+///
+/// Derefs to `str`. Use [`into_string`](Self::into_string) to keep the buffer
+/// instead.
+pub struct PooledString {
+    buf: String,
+}
+
+impl PooledString {
+    /// Takes the buffer out of the pool for good.
+    #[inline]
+    pub fn into_string(mut self) -> String {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.buf
+    }
+}
+
+impl AsRef<str> for PooledString {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.buf
+    }
+}
+
+impl fmt::Debug for PooledString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.buf, f)
+    }
+}
+
+impl fmt::Display for PooledString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.buf)
+    }
+}
+
+impl Drop for PooledString {
+    fn drop(&mut self) {
+        give_back(std::mem::take(&mut self.buf));
+    }
+}
+
+/// Takes an empty buffer from the pool, or a new one if it is empty.
+fn take() -> String {
+    POOL.try_with(|pool| pool.borrow_mut().pop())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| String::with_capacity(128))
+}
+
+/// Returns `buf` to the pool, unless it is too large or the pool is full.
+fn give_back(mut buf: String) {
+    // `into_string` leaves an unallocated buffer behind.
+    if buf.capacity() == 0 || buf.capacity() > MAX_POOLED_CAPACITY {
+        return;
+    }
+    buf.clear();
+    // The pool is gone if the thread is shutting down. The buffer is freed then.
+    let _ = POOL.try_with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if pool.len() < MAX_POOLED_BUFFERS {
+            pool.push(buf);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn pooled() -> usize {
+        POOL.with(|pool| pool.borrow().len())
+    }
+
+    #[test]
+    fn test_reuse() {
+        let value = BTreeMap::from([("name", "John Doe")]);
+
+        let first = to_pooled_string(&value).unwrap();
+        assert_eq!(&*first, crate::to_string(&value).unwrap());
+        let ptr = first.as_ptr();
+        drop(first);
+
+        let second = to_pooled_string(&BTreeMap::from([("id", 1)])).unwrap();
+        assert_eq!(second.as_ptr(), ptr);
+        assert_eq!(&*second, "id=1");
+    }
+
+    #[test]
+    fn test_error_returns_buffer() {
+        drop(to_pooled_string(&BTreeMap::from([("a", 1)])).unwrap());
+        let before = pooled();
+        assert!(to_pooled_string(&1).is_err());
+        assert_eq!(pooled(), before);
+    }
+
+    #[test]
+    fn test_oversized_not_retained() {
+        let large = BTreeMap::from([("data", "x".repeat(MAX_POOLED_CAPACITY))]);
+        let before = pooled();
+        let pooled_large = to_pooled_string(&large).unwrap();
+        assert!(pooled_large.len() > MAX_POOLED_CAPACITY);
+        drop(pooled_large);
+        // The buffer it was written to was taken from the pool and not returned.
+        assert_eq!(pooled(), before.saturating_sub(1));
+    }
+
+    #[test]
+    fn test_into_string() {
+        let owned = to_pooled_string(&BTreeMap::from([("a", 1)]))
+            .unwrap()
+            .into_string();
+        assert_eq!(owned, "a=1");
+    }
+}
@@ -0,0 +1,96 @@
+//! Values that are already JSON text.
+
+use serde::{Serialize, Serializer};
+
+/// The name of the newtype struct raw JSON text is passed in.
+///
+/// [`JsonSerializer`](crate::json::JsonSerializer) writes the text of such a
+/// struct as is. Other serializers see a newtype struct holding a string.
+pub(crate) const RAW_TOKEN: &str = "$serde_metaform::private::RawJson";
+
+/// JSON text that is written as is, rather than as a JSON string.
+///
+/// Use it for values that arrive pre-encoded, like a template payload stored as
+/// a JSON string. A plain `String` holding `{"key":"value"}` is written nested
+/// as the string `"{\"key\":\"value\"}"`; wrapped in `RawJson`, it is written
+/// as the object itself. The text is still percent-encoded, but never checked:
+/// it must be valid JSON.
+///
+/// Serializers other than this crate's see a newtype struct holding a string,
+/// and write the text as a string.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::RawJson;
+///
+/// #[derive(Serialize)]
+/// struct Batch { requests: Vec<RawJson<&'static str>> }
+///
+/// let batch = Batch { requests: vec![RawJson(r#"{"method":"GET"}"#)] };
+/// assert_eq!(
+///     serde_metaform::to_string(&batch).unwrap(),
+///     "requests=%5B%7B%22method%22%3A%22GET%22%7D%5D"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RawJson<T>(pub T);
+
+impl<T: AsRef<str>> Serialize for RawJson<T> {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW_TOKEN, self.0.as_ref())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::prelude::rust_2024::*;
+
+    #[derive(Serialize)]
+    struct Template {
+        name: &'static str,
+        components: RawJson<String>,
+    }
+
+    #[test]
+    fn test_raw_json() {
+        let template = Template {
+            name: "hello",
+            components: RawJson(r#"[{"type":"body","text":"a b"}]"#.to_owned()),
+        };
+        assert_eq!(
+            crate::to_string(&template).unwrap(),
+            "name=hello&components=%5B%7B%22type%22%3A%22body%22%2C%22text%22%3A%22a%20b%22%7D%5D"
+        );
+
+        // Nested, the text isn't quoted either.
+        let nested = BTreeMap::from([("template", &template)]);
+        let encoded = crate::to_string(&nested).unwrap();
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+        assert_eq!(
+            decoded[0].1,
+            r#"{"name":"hello","components":[{"type":"body","text":"a b"}]}"#
+        );
+
+        // Stringified, the text becomes the content of the string.
+        let encoded = crate::FormEncoder::new()
+            .force_json_string_values(true)
+            .encode(&template)
+            .unwrap();
+        let decoded: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+        assert_eq!(decoded[1].1, r#""[{\"type\":\"body\",\"text\":\"a b\"}]""#);
+
+        // Other serializers write a string.
+        assert_eq!(
+            serde_json::to_string(&template.components).unwrap(),
+            r#""[{\"type\":\"body\",\"text\":\"a b\"}]""#
+        );
+    }
+}
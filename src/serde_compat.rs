@@ -0,0 +1,203 @@
+//! Compatibility suite for combinations of serde attributes.
+//!
+//! Each case pairs a type using several attributes at once (`tag`,
+//! `rename_all`, `flatten`, `skip_serializing_if`, ...) with its confirmed
+//! form output. Every value is also decoded and compared with what
+//! `serde_json` writes for the same field.
+
+use std::collections::BTreeMap;
+use std::{string::String, vec::Vec};
+
+use serde::Serialize;
+
+use crate::to_string;
+
+/// Asserts the form output of `value`, and that every value decodes to the
+/// same JSON `serde_json` writes for that field.
+fn assert_form<T: Serialize>(value: &T, expected: &str) {
+    use serde_json::Value;
+
+    let encoded = to_string(value).unwrap();
+    assert_eq!(encoded, expected);
+
+    let Value::Object(fields) = serde_json::to_value(value).unwrap() else {
+        panic!("not an object");
+    };
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(&encoded).unwrap();
+    assert_eq!(pairs.len(), fields.len());
+    for (key, decoded) in pairs {
+        let decoded = match &fields[&key] {
+            // Top-level strings are written bare.
+            Value::String(_) => Value::String(decoded),
+            _ => serde_json::from_str(&decoded).unwrap(),
+        };
+        assert_eq!(decoded, fields[&key], "value of `{key}`");
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Attachment {
+    media_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption_text: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+enum Body {
+    PlainText { body_text: &'static str },
+    Media(Attachment),
+    Reaction { message_id: u64, emoji: char },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    messaging_product: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<u64>,
+    #[serde(flatten)]
+    body: Body,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    recipient_ids: Vec<u64>,
+}
+
+#[test]
+fn test_tag_rename_all_flatten() {
+    assert_form(
+        &Envelope {
+            messaging_product: "whatsapp",
+            reply_to: None,
+            body: Body::PlainText {
+                body_text: "hi & bye",
+            },
+            recipient_ids: vec![],
+        },
+        "messagingProduct=whatsapp&type=plain_text&bodyText=hi%20%26%20bye",
+    );
+    assert_form(
+        &Envelope {
+            messaging_product: "whatsapp",
+            reply_to: Some(7),
+            body: Body::Media(Attachment {
+                media_type: "image/png",
+                caption_text: Some("a \"cat\""),
+            }),
+            recipient_ids: vec![1, 2],
+        },
+        "messagingProduct=whatsapp&replyTo=7&type=media&mediaType=image%2Fpng\
+         &captionText=a%20%22cat%22&recipientIds=%5B1%2C2%5D",
+    );
+    assert_form(
+        &Envelope {
+            messaging_product: "whatsapp",
+            reply_to: None,
+            body: Body::Reaction {
+                message_id: 9,
+                emoji: '👍',
+            },
+            recipient_ids: vec![],
+        },
+        "messagingProduct=whatsapp&type=reaction&messageId=9&emoji=%F0%9F%91%8D",
+    );
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "SCREAMING_SNAKE_CASE")]
+enum Adjacent {
+    Ping,
+    Move { dx: i8, dy: i8 },
+    Label(&'static str),
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Untagged {
+    Id(u64),
+    Named { name: &'static str },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Commands {
+    first_command: Adjacent,
+    second_command: Adjacent,
+    third_command: Adjacent,
+    targets: Vec<Untagged>,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    internal: u8,
+}
+
+#[test]
+fn test_adjacent_and_untagged_in_values() {
+    assert_form(
+        &Commands {
+            first_command: Adjacent::Ping,
+            second_command: Adjacent::Move { dx: -1, dy: 2 },
+            third_command: Adjacent::Label("x"),
+            targets: vec![Untagged::Id(3), Untagged::Named { name: "n" }],
+            internal: 0,
+        },
+        "first-command=%7B%22kind%22%3A%22PING%22%7D\
+         &second-command=%7B%22kind%22%3A%22MOVE%22%2C%22data%22%3A%7B%22dx%22%3A-1%2C%22dy%22%3A2%7D%7D\
+         &third-command=%7B%22kind%22%3A%22LABEL%22%2C%22data%22%3A%22x%22%7D\
+         &targets=%5B3%2C%7B%22name%22%3A%22n%22%7D%5D",
+    );
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    item_id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit_price: Option<f64>,
+    #[serde(flatten)]
+    attributes: BTreeMap<&'static str, &'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Operation {
+    Add(Item),
+    Remove { item_id: u32 },
+}
+
+#[derive(Serialize)]
+struct Batch {
+    operations: Vec<Operation>,
+    #[serde(rename = "dry-run", skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
+}
+
+#[test]
+fn test_tagged_flatten_in_nested_values() {
+    assert_form(
+        &Batch {
+            operations: vec![
+                Operation::Add(Item {
+                    item_id: 1,
+                    unit_price: Some(9.5),
+                    attributes: BTreeMap::from([("color", "red"), ("size", "M")]),
+                }),
+                Operation::Add(Item {
+                    item_id: 2,
+                    unit_price: None,
+                    attributes: BTreeMap::new(),
+                }),
+                Operation::Remove { item_id: 3 },
+            ],
+            dry_run: true,
+        },
+        "operations=%5B%7B%22op%22%3A%22add%22%2C%22itemId%22%3A1%2C%22unitPrice%22%3A9.5\
+         %2C%22color%22%3A%22red%22%2C%22size%22%3A%22M%22%7D\
+         %2C%7B%22op%22%3A%22add%22%2C%22itemId%22%3A2%7D\
+         %2C%7B%22op%22%3A%22remove%22%2C%22item_id%22%3A3%7D%5D\
+         &dry-run=true",
+    );
+}
@@ -0,0 +1,200 @@
+//! Fields that can be left out when they hold their default value.
+
+use serde::{Serialize, Serializer};
+
+use crate::{
+    error::{Error, ErrorInner},
+    error_unsupported,
+};
+
+/// The name of the newtype struct a [`DefaultSkip`] holding its default value
+/// is passed in.
+///
+/// The top-level [`Serializer`](crate::Serializer) leaves out pairs whose value
+/// is such a struct when [`skip_default_values`](crate::Serializer::skip_default_values)
+/// is enabled. Everywhere else, it is written as the value it holds.
+pub(crate) const DEFAULT_TOKEN: &str = "$serde_metaform::private::DefaultSkip";
+
+/// A field that is left out of the payload when it equals `T::default()`.
+///
+/// Only pairs are left out, and only when the serializer has
+/// [`skip_default_values`](crate::Serializer::skip_default_values) enabled.
+/// Otherwise, and nested in JSON values, it is written as the value it holds,
+/// by this crate and by any other serializer.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::{DefaultSkip, FormEncoder};
+///
+/// #[derive(Serialize)]
+/// struct Query { q: &'static str, page: DefaultSkip<u32>, exact: DefaultSkip<bool> }
+///
+/// let query = Query { q: "rust", page: DefaultSkip(0), exact: DefaultSkip(true) };
+/// let encoder = FormEncoder::new().skip_default_values(true);
+/// assert_eq!(encoder.encode(&query).unwrap(), "q=rust&exact=true");
+/// assert_eq!(serde_metaform::to_string(&query).unwrap(), "q=rust&page=0&exact=true");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DefaultSkip<T>(pub T);
+
+impl<T> Serialize for DefaultSkip<T>
+where
+    T: Serialize + Default + PartialEq,
+{
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.0 == T::default() {
+            serializer.serialize_newtype_struct(DEFAULT_TOKEN, &self.0)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+/// Returns `true` if `value` is a [`DefaultSkip`] holding its default value.
+///
+/// Only the outermost call is looked at, so this returns right away for any
+/// other value.
+#[inline]
+pub(crate) fn is_skipped_default<T>(value: &T) -> bool
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(DefaultProbe).is_ok()
+}
+
+/// Succeeds only for the newtype struct a skipped default is passed in.
+struct DefaultProbe;
+
+/// The probe's answer for any other value.
+///
+/// Unlike the error constructors, this is the common case, so it is not cold.
+#[inline]
+const fn not_skipped(got: &'static str) -> Error {
+    Error {
+        inner: ErrorInner::NotAnObject(got),
+    }
+}
+
+impl Serializer for DefaultProbe {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, name: &'static str, _value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == DEFAULT_TOKEN {
+            Ok(())
+        } else {
+            Err(not_skipped("NewtypeStruct"))
+        }
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, _value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(not_skipped("Option::<T>::Some"))
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        Err(not_skipped("UnitVariant"))
+    }
+
+    /// Fails like `serialize_str`, without formatting `value` first.
+    #[inline]
+    fn collect_str<T>(self, _value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + core::fmt::Display,
+    {
+        Err(not_skipped("str"))
+    }
+
+    error_unsupported! {
+        not_skipped, [bool integers floats char str bytes empty array object]
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{FormEncoder, Serializer as FormSerializer};
+    use std::collections::BTreeMap;
+    use std::prelude::rust_2024::*;
+
+    #[derive(Serialize)]
+    struct Settings {
+        name: DefaultSkip<String>,
+        count: DefaultSkip<u32>,
+        enabled: DefaultSkip<bool>,
+        tags: DefaultSkip<Vec<&'static str>>,
+        limit: u32,
+    }
+
+    #[test]
+    fn test_skip_default_values() {
+        let defaults = Settings {
+            name: DefaultSkip(String::new()),
+            count: DefaultSkip(0),
+            enabled: DefaultSkip(false),
+            tags: DefaultSkip(vec![]),
+            limit: 0,
+        };
+        let set = Settings {
+            name: DefaultSkip("a b".to_owned()),
+            count: DefaultSkip(2),
+            enabled: DefaultSkip(true),
+            tags: DefaultSkip(vec!["x"]),
+            limit: 0,
+        };
+
+        let encoder = FormEncoder::new().skip_default_values(true);
+        // Plain fields are always written.
+        assert_eq!(encoder.encode(&defaults).unwrap(), "limit=0");
+        assert_eq!(
+            encoder.encode(&set).unwrap(),
+            "name=a%20b&count=2&enabled=true&tags=%5B%22x%22%5D&limit=0"
+        );
+
+        // Disabled, the values are written as usual.
+        assert_eq!(
+            crate::to_string(&defaults).unwrap(),
+            "name=&count=0&enabled=false&tags=%5B%5D&limit=0"
+        );
+
+        // Map values and flat mode too.
+        let mut out = String::new();
+        let ser = FormSerializer::new(&mut out)
+            .skip_default_values(true)
+            .flat_strict(true);
+        BTreeMap::from([("a", DefaultSkip(0)), ("b", DefaultSkip(1))])
+            .serialize(ser)
+            .unwrap();
+        assert_eq!(out, "b=1");
+    }
+
+    #[test]
+    fn test_nested_default_values_are_written() {
+        let nested = BTreeMap::from([("inner", BTreeMap::from([("count", DefaultSkip(0))]))]);
+        let encoder = FormEncoder::new().skip_default_values(true);
+        assert_eq!(
+            encoder.encode(&nested).unwrap(),
+            "inner=%7B%22count%22%3A0%7D"
+        );
+        assert_eq!(serde_json::to_string(&DefaultSkip(0)).unwrap(), "0");
+    }
+}
@@ -0,0 +1,86 @@
+//! Maps written with their keys in sorted order.
+
+use alloc::vec::Vec;
+
+use serde::{Serialize, Serializer, ser::SerializeMap};
+
+/// A map that serializes with its keys in sorted order.
+///
+/// Maps are written in the order they iterate in, which for a `HashMap` changes
+/// from run to run. Wrap a map in `SortedMap` for canonical output, e.g. for
+/// payloads that get signed or used as cache keys. Keys are compared bytewise,
+/// and entries with equal keys keep their relative order.
+///
+/// This works both for the top-level pairs and for nested JSON objects, and
+/// with any serializer, not just this crate's. The entries are collected and
+/// sorted each time the map is serialized.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use std::collections::HashMap;
+/// use serde_metaform::SortedMap;
+///
+/// #[derive(Serialize)]
+/// struct Request { headers: SortedMap<HashMap<&'static str, &'static str>> }
+///
+/// let headers = HashMap::from([("b", "2"), ("a", "1")]);
+/// assert_eq!(
+///     serde_metaform::to_string(&Request { headers: SortedMap(headers.clone()) }).unwrap(),
+///     "headers=%7B%22a%22%3A%221%22%2C%22b%22%3A%222%22%7D"
+/// );
+/// assert_eq!(serde_metaform::to_string(&SortedMap(headers)).unwrap(), "a=1&b=2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SortedMap<M>(pub M);
+
+impl<M, K, V> Serialize for SortedMap<M>
+where
+    for<'a> &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: AsRef<str> + Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entries: Vec<_> = (&self.0).into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (key, value) in entries {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+    use std::prelude::rust_2024::*;
+
+    #[test]
+    fn test_sorted_map() {
+        // Bytewise, so uppercase sorts first.
+        let map = HashMap::from([("b", 2), ("a", 1), ("B", 3)]);
+        assert_eq!(
+            crate::to_string(&BTreeMap::from([("m", SortedMap(map))])).unwrap(),
+            "m=%7B%22B%22%3A3%2C%22a%22%3A1%2C%22b%22%3A2%7D"
+        );
+
+        // Owned keys, same output as a `BTreeMap`, at the top level too.
+        let map = HashMap::from([("z".to_owned(), vec![1]), ("m".to_owned(), vec![])]);
+        let sorted: BTreeMap<_, _> = map.clone().into_iter().collect();
+        assert_eq!(
+            crate::to_string(&SortedMap(map.clone())).unwrap(),
+            crate::to_string(&sorted).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&SortedMap(map)).unwrap(),
+            r#"{"m":[],"z":[1]}"#
+        );
+    }
+}
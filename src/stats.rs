@@ -0,0 +1,504 @@
+//! Size and shape statistics of a serialized payload.
+
+use alloc::string::String;
+use core::fmt::{Display, Write};
+
+use serde::{Serialize, ser};
+
+use crate::{
+    Serializer, StructVariantSerializer, TupleVariantSerializer,
+    error::{Error, top_level_must_be_object},
+    error_unsupported,
+};
+
+/// Numbers describing a payload, as returned by [`to_string_with_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct SerializeStats {
+    /// The number of `key=value` pairs.
+    pub pairs: usize,
+    /// The length of the payload in bytes.
+    pub bytes: usize,
+    /// The deepest nesting of JSON arrays and objects in any value: `0` when
+    /// every value is a scalar, `1` for a list of scalars, and so on.
+    pub max_depth: usize,
+    /// The bytes added by percent-encoding: two for every byte written as
+    /// `%XX`.
+    pub encoded_overhead: usize,
+}
+
+/// Serializes the given data structure as a `String`, along with
+/// [`SerializeStats`] about it.
+///
+/// The output is the same as [`to_string`](crate::to_string)'s, and the value
+/// is serialized only once. This is meant for capacity planning and
+/// payload-size alerting.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// #[derive(Serialize)]
+/// struct Profile { username: &'static str, tags: Vec<&'static str> }
+///
+/// #[derive(Serialize)]
+/// struct Update { user_id: u32, profile: Profile }
+///
+/// let update = Update {
+///     user_id: 123,
+///     profile: Profile { username: "jdoe", tags: vec!["rust", "serde"] },
+/// };
+/// let (body, stats) = serde_metaform::to_string_with_stats(&update).unwrap();
+/// assert_eq!(stats.pairs, 2);
+/// assert_eq!(stats.bytes, body.len());
+/// // `{"tags":[...]}`
+/// assert_eq!(stats.max_depth, 2);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if serialization fails. See [`to_writer`](crate::to_writer)
+/// for details.
+pub fn to_string_with_stats<T>(value: &T) -> Result<(String, SerializeStats), Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = Escapes {
+        output: String::with_capacity(128),
+        count: 0,
+    };
+    let mut stats = SerializeStats::default();
+    value.serialize(Collect {
+        ser: Serializer::new(&mut output),
+        stats: &mut stats,
+    })?;
+    stats.bytes = output.output.len();
+    stats.encoded_overhead = output.count * 2;
+    Ok((output.output, stats))
+}
+
+/// A writer that counts the `%XX` escapes written to it.
+///
+/// Keys and values encode `%` itself, so every `%` starts an escape.
+struct Escapes {
+    output: String,
+    count: usize,
+}
+
+impl Write for Escapes {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.count += s.bytes().filter(|&byte| byte == b'%').count();
+        self.output.push_str(s);
+        Ok(())
+    }
+}
+
+/// A top-level [`Serializer`] that copies its counters to `stats` once the
+/// payload is written.
+///
+/// `serialize` consumes the serializer it is given, so the counters have to be
+/// read by the serializer itself, at the end.
+struct Collect<'s, W> {
+    ser: Serializer<W>,
+    stats: &'s mut SerializeStats,
+}
+
+impl<'s, W: Write> ser::Serializer for Collect<'s, W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeTupleVariant = CollectVariant<'s, TupleVariantSerializer<W>>;
+    type SerializeStructVariant = CollectVariant<'s, StructVariantSerializer<W>>;
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.ser.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), Error> {
+        self.ser.serialize_unit_struct(name)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.ser
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<(), Error> {
+        self.ser.serialize_none()
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        use ser::SerializeMap as _;
+
+        let mut map = self.serialize_map(Some(1))?;
+        map.serialize_entry(variant, value)?;
+        map.end()
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(CollectVariant {
+            inner: self
+                .ser
+                .serialize_tuple_variant(name, variant_index, variant, len)?,
+            stats: self.stats,
+        })
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(CollectVariant {
+            inner: self
+                .ser
+                .serialize_struct_variant(name, variant_index, variant, len)?,
+            stats: self.stats,
+        })
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+        Ok(Self {
+            ser: self.ser.serialize_map(len)?,
+            stats: self.stats,
+        })
+    }
+
+    #[inline]
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self, Error> {
+        Ok(Self {
+            ser: self.ser.serialize_struct(name, len)?,
+            stats: self.stats,
+        })
+    }
+
+    #[inline]
+    fn collect_str<T>(self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Display,
+    {
+        self.ser.collect_str(value)
+    }
+
+    error_unsupported! {
+        top_level_must_be_object, [bool integers floats char str bytes array]
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Collect<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.ser.serialize_key(key)
+    }
+
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.ser.serialize_value(value)
+    }
+
+    #[inline]
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), Error>
+    where
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
+    {
+        self.ser.serialize_entry(key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), Error> {
+        self.stats.pairs = self.ser.pairs;
+        self.stats.max_depth = self.ser.max_depth;
+        ser::SerializeMap::end(self.ser)
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Collect<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(&mut self.ser, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// A top-level tuple or struct variant, written as a single pair.
+struct CollectVariant<'s, V> {
+    inner: V,
+    stats: &'s mut SerializeStats,
+}
+
+impl<W: Write> ser::SerializeTupleVariant for CollectVariant<'_, TupleVariantSerializer<W>> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner.serialize_field(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), Error> {
+        self.stats.pairs = 1;
+        self.stats.max_depth = self.inner.max_depth();
+        self.inner.end()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for CollectVariant<'_, StructVariantSerializer<W>> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner.serialize_field(key, value)
+    }
+
+    #[inline]
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        self.inner.skip_field(key)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), Error> {
+        self.stats.pairs = 1;
+        self.stats.max_depth = self.inner.max_depth();
+        self.inner.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::prelude::rust_2024::*;
+
+    // The example from the README's "Usage" section.
+    #[derive(Serialize)]
+    struct Message<'a> {
+        recipient: &'a str,
+        content: Content<'a>,
+    }
+
+    #[derive(Serialize)]
+    struct Content<'a> {
+        #[serde(rename = "type")]
+        message_type: &'a str,
+        text: &'a str,
+        buttons: Vec<Button<'a>>,
+    }
+
+    #[derive(Serialize)]
+    struct Button<'a> {
+        id: &'a str,
+        title: &'a str,
+    }
+
+    #[test]
+    fn test_readme_examples() {
+        let message = Message {
+            recipient: "1234567890",
+            content: Content {
+                message_type: "interactive",
+                text: "Choose an option:",
+                buttons: vec![
+                    Button {
+                        id: "opt1",
+                        title: "Option 1",
+                    },
+                    Button {
+                        id: "opt2",
+                        title: "Option 2",
+                    },
+                ],
+            },
+        };
+        let (body, stats) = to_string_with_stats(&message).unwrap();
+        assert_eq!(body, crate::to_string(&message).unwrap());
+        assert_eq!(
+            stats,
+            SerializeStats {
+                pairs: 2,
+                bytes: 258,
+                // `{"buttons":[{...}]}`
+                max_depth: 3,
+                // 156 bytes decoded.
+                encoded_overhead: 102,
+            }
+        );
+
+        // The "Form + JSON" example from the introduction.
+        #[derive(Serialize)]
+        struct Profile {
+            username: &'static str,
+            tags: Vec<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct User {
+            user_id: u32,
+            profile: Profile,
+        }
+
+        let user = User {
+            user_id: 123,
+            profile: Profile {
+                username: "jdoe",
+                tags: vec!["rust", "serde"],
+            },
+        };
+        let (body, stats) = to_string_with_stats(&user).unwrap();
+        assert_eq!(
+            body,
+            "user_id=123&profile=%7B%22username%22%3A%22jdoe%22%2C%22tags%22%3A%5B%22rust%22%2C%22serde%22%5D%7D"
+        );
+        assert_eq!(
+            stats,
+            SerializeStats {
+                pairs: 2,
+                bytes: 99,
+                max_depth: 2,
+                encoded_overhead: 2 * 18,
+            }
+        );
+    }
+
+    #[test]
+    fn test_scalars_and_empty() {
+        let (body, stats) = to_string_with_stats(&BTreeMap::from([("a b", "c")])).unwrap();
+        assert_eq!(body, "a%20b=c");
+        assert_eq!(
+            stats,
+            SerializeStats {
+                pairs: 1,
+                bytes: 7,
+                max_depth: 0,
+                encoded_overhead: 2,
+            }
+        );
+
+        let empty = BTreeMap::<&str, u8>::new();
+        assert_eq!(
+            to_string_with_stats(&empty).unwrap(),
+            (String::new(), SerializeStats::default())
+        );
+    }
+
+    #[test]
+    fn test_variants() {
+        #[derive(Serialize)]
+        enum Event {
+            Moved(i32, [u8; 2]),
+            Renamed { names: Vec<Vec<&'static str>> },
+            Tagged(BTreeMap<&'static str, [u8; 0]>),
+        }
+
+        let (body, stats) = to_string_with_stats(&Event::Moved(1, [2, 3])).unwrap();
+        assert_eq!(body, "Moved=%5B1%2C%5B2%2C3%5D%5D");
+        assert_eq!((stats.pairs, stats.max_depth), (1, 2));
+
+        let renamed = Event::Renamed {
+            names: vec![vec!["a"]],
+        };
+        let (body, stats) = to_string_with_stats(&renamed).unwrap();
+        assert_eq!(body, "Renamed=%7B%22names%22%3A%5B%5B%22a%22%5D%5D%7D");
+        assert_eq!((stats.pairs, stats.max_depth), (1, 3));
+
+        let tagged = Event::Tagged(BTreeMap::from([("a", [])]));
+        let (body, stats) = to_string_with_stats(&tagged).unwrap();
+        assert_eq!(body, "Tagged=%7B%22a%22%3A%5B%5D%7D");
+        assert_eq!((stats.pairs, stats.max_depth), (1, 2));
+    }
+
+    #[test]
+    fn test_serializes_once() {
+        use std::cell::Cell;
+
+        struct Counted<'a>(&'a Cell<u32>);
+
+        impl Serialize for Counted<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.0.set(self.0.get() + 1);
+                BTreeMap::from([("a", 1)]).serialize(serializer)
+            }
+        }
+
+        let calls = Cell::new(0);
+        let (body, stats) = to_string_with_stats(&Counted(&calls)).unwrap();
+        assert_eq!((body.as_str(), stats.pairs), ("a=1", 1));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,327 @@
+//! Helpers for testing "Form + JSON" output.
+//!
+//! This module is only available with the `test-utils` feature enabled. It
+//! compares payloads by what they decode to, so tests don't have to spell out
+//! percent-encoded strings or depend on key order.
+
+use std::collections::BTreeMap;
+
+use serde::ser::Error as _;
+use serde_json::Value;
+
+use crate::{error::Error, is_valid_form_string};
+
+/// Decodes a payload into its keys and their values.
+///
+/// Each value is parsed as JSON. Values that aren't JSON, such as top-level
+/// strings, which are written bare, are kept as [`Value::String`]. Note that a
+/// bare string that happens to be valid JSON, like `123` or `true`, is parsed
+/// as such. If a key occurs more than once, its last value wins.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use serde_metaform::test_utils::decode_form;
+///
+/// let form = decode_form("name=John%20Doe&tags=%5B%22a%22%5D");
+/// assert_eq!(form["name"], json!("John Doe"));
+/// assert_eq!(form["tags"], json!(["a"]));
+/// ```
+pub fn decode_form(s: &str) -> BTreeMap<String, Value> {
+    form_urlencoded::parse(s.as_bytes())
+        .map(|(key, value)| (key.into_owned(), decode_value(&value)))
+        .collect()
+}
+
+/// Asserts that two payloads decode to the same keys and values.
+///
+/// Key order and the details of the encoding, like `%20` versus `+`, don't
+/// matter. See [`decode_form`] for how values are compared.
+///
+/// # Panics
+///
+/// Panics if the decoded payloads differ, showing both payloads.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_metaform::test_utils::assert_form_eq;
+///
+/// assert_form_eq("b=%7B%22x%22%3A1%7D&a=hi+there", "a=hi%20there&b=%7B%22x%22:1%7D");
+/// ```
+#[track_caller]
+pub fn assert_form_eq(actual: &str, expected: &str) {
+    assert_eq!(
+        decode_form(actual),
+        decode_form(expected),
+        "forms differ\n  actual: {actual}\nexpected: {expected}"
+    );
+}
+
+/// Returns the decoded value of `key` in a payload, or `None` if it is absent.
+///
+/// The value is decoded like in [`decode_form`]. If the key occurs more than
+/// once, the first value is returned.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use serde_metaform::test_utils::form_field;
+///
+/// let encoded = "id=1&batch=%5B%7B%22method%22%3A%22GET%22%7D%5D";
+/// assert_eq!(form_field(encoded, "batch"), Some(json!([{"method": "GET"}])));
+/// assert_eq!(form_field(encoded, "missing"), None);
+/// ```
+pub fn form_field(encoded: &str, key: &str) -> Option<Value> {
+    form_urlencoded::parse(encoded.as_bytes())
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| decode_value(&value))
+}
+
+/// A difference between two payloads, as found by [`diff`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PairDiff {
+    /// `key` is only in the second payload.
+    Added { key: String, value: Value },
+    /// `key` is only in the first payload.
+    Removed { key: String, value: Value },
+    /// `key` is in both payloads, with different values.
+    Changed {
+        key: String,
+        before: Value,
+        after: Value,
+    },
+    /// The keys both payloads have come in a different order. Each list holds
+    /// them in the order of one payload.
+    Reordered {
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+}
+
+/// Compares two payloads pair by pair.
+///
+/// Also re-exported at the crate root, as `serde_metaform::diff`.
+///
+/// Keys are compared decoded, and values as decoded by [`decode_form`], so a
+/// change in encoding alone, or in the order of keys in a JSON object, is not
+/// a difference. Removed and changed keys are listed in the order of `a`,
+/// then added keys in the order of `b`. A change in the order of the keys
+/// both payloads have comes last, as a single [`PairDiff::Reordered`].
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use serde_metaform::{PairDiff, diff};
+///
+/// let before = "id=1&user=%7B%22name%22%3A%22a%22%7D";
+/// let after = "user=%7B%22name%22%3A%22b%22%7D&id=1&page=2";
+/// assert_eq!(
+///     diff(before, after).unwrap(),
+///     [
+///         PairDiff::Changed {
+///             key: "user".to_owned(),
+///             before: json!({"name": "a"}),
+///             after: json!({"name": "b"}),
+///         },
+///         PairDiff::Added { key: "page".to_owned(), value: json!(2) },
+///         PairDiff::Reordered {
+///             before: vec!["id".to_owned(), "user".to_owned()],
+///             after: vec!["user".to_owned(), "id".to_owned()],
+///         },
+///     ]
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if either payload isn't well-formed, as checked by
+/// [`is_valid_form_string`](crate::is_valid_form_string).
+pub fn diff(a: &str, b: &str) -> Result<Vec<PairDiff>, Error> {
+    if !is_valid_form_string(a) {
+        return Err(Error::custom("the first payload is not well-formed"));
+    }
+    if !is_valid_form_string(b) {
+        return Err(Error::custom("the second payload is not well-formed"));
+    }
+    let (before, after) = (decode_form(a), decode_form(b));
+
+    let mut diffs = Vec::new();
+    for key in key_order(a) {
+        match (&before[&key], after.get(&key)) {
+            (value, None) => diffs.push(PairDiff::Removed {
+                key,
+                value: value.clone(),
+            }),
+            (value, Some(other)) if value != other => diffs.push(PairDiff::Changed {
+                key,
+                before: value.clone(),
+                after: other.clone(),
+            }),
+            _ => {}
+        }
+    }
+    for key in key_order(b) {
+        if !before.contains_key(&key) {
+            let value = after[&key].clone();
+            diffs.push(PairDiff::Added { key, value });
+        }
+    }
+
+    let common_before: Vec<_> = key_order(a)
+        .into_iter()
+        .filter(|key| after.contains_key(key))
+        .collect();
+    let common_after: Vec<_> = key_order(b)
+        .into_iter()
+        .filter(|key| before.contains_key(key))
+        .collect();
+    if common_before != common_after {
+        diffs.push(PairDiff::Reordered {
+            before: common_before,
+            after: common_after,
+        });
+    }
+    Ok(diffs)
+}
+
+/// Returns the decoded keys of a payload, in the order they first occur.
+fn key_order(s: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for (key, _) in form_urlencoded::parse(s.as_bytes()) {
+        if !keys.iter().any(|k| *k == key) {
+            keys.push(key.into_owned());
+        }
+    }
+    keys
+}
+
+/// Parses a value as JSON, falling back to a string.
+fn decode_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Message {
+        to: &'static str,
+        template: Template,
+    }
+
+    #[derive(Serialize)]
+    struct Template {
+        name: &'static str,
+        lang: [&'static str; 2],
+    }
+
+    fn message() -> Message {
+        Message {
+            to: "+1 555",
+            template: Template {
+                name: "hello",
+                lang: ["en", "US"],
+            },
+        }
+    }
+
+    #[test]
+    fn test_decode_form() {
+        let form = decode_form(&crate::to_string(&message()).unwrap());
+        assert_eq!(
+            form,
+            BTreeMap::from([
+                ("to".to_owned(), json!("+1 555")),
+                (
+                    "template".to_owned(),
+                    json!({"name": "hello", "lang": ["en", "US"]})
+                ),
+            ])
+        );
+        assert!(decode_form("").is_empty());
+    }
+
+    #[test]
+    fn test_assert_form_eq() {
+        let encoded = crate::to_string(&message()).unwrap();
+        // Same pairs, in a different order and encoding.
+        assert_form_eq(
+            &encoded,
+            "template=%7B%22lang%22:[%22en%22,%22US%22],%22name%22:%22hello%22%7D&to=%2B1+555",
+        );
+    }
+
+    #[test]
+    #[should_panic = "forms differ"]
+    fn test_assert_form_eq_mismatch() {
+        assert_form_eq("a=1&b=2", "a=1&b=3");
+    }
+
+    #[test]
+    fn test_diff() {
+        let encoded = crate::to_string(&message()).unwrap();
+        assert_eq!(diff(&encoded, &encoded).unwrap(), []);
+
+        // An added key.
+        let added = format!("{encoded}&page=2");
+        assert_eq!(
+            diff(&encoded, &added).unwrap(),
+            [PairDiff::Added {
+                key: "page".to_owned(),
+                value: json!(2),
+            }]
+        );
+        assert_eq!(
+            diff(&added, &encoded).unwrap(),
+            [PairDiff::Removed {
+                key: "page".to_owned(),
+                value: json!(2),
+            }]
+        );
+
+        // A changed nested field.
+        let mut changed = message();
+        changed.template.lang[1] = "GB";
+        assert_eq!(
+            diff(&encoded, &crate::to_string(&changed).unwrap()).unwrap(),
+            [PairDiff::Changed {
+                key: "template".to_owned(),
+                before: json!({"name": "hello", "lang": ["en", "US"]}),
+                after: json!({"name": "hello", "lang": ["en", "GB"]}),
+            }]
+        );
+
+        // The same pairs, reordered and encoded differently.
+        let reordered =
+            "template=%7B%22lang%22:[%22en%22,%22US%22],%22name%22:%22hello%22%7D&to=%2B1+555";
+        assert_eq!(
+            diff(&encoded, reordered).unwrap(),
+            [PairDiff::Reordered {
+                before: vec!["to".to_owned(), "template".to_owned()],
+                after: vec!["template".to_owned(), "to".to_owned()],
+            }]
+        );
+
+        let err = diff(&encoded, "id=%ZZ").unwrap_err();
+        assert_eq!(err.message(), Some("the second payload is not well-formed"));
+    }
+
+    #[test]
+    fn test_form_field() {
+        let encoded = crate::to_string(&message()).unwrap();
+        assert_eq!(form_field(&encoded, "to"), Some(json!("+1 555")));
+        assert_eq!(
+            form_field(&encoded, "template").unwrap()["lang"][1],
+            json!("US")
+        );
+        assert_eq!(form_field(&encoded, "name"), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! [`SystemTime`] values written as RFC 3339 timestamps.
+
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Serialize, Serializer, ser::Error as _};
+
+/// A [`SystemTime`] that serializes as an RFC 3339 timestamp in UTC.
+///
+/// `SystemTime` doesn't implement `Serialize`. This newtype writes it as a
+/// string like `2024-01-01T00:00:00Z`: bare as a top-level value, and as a
+/// JSON string when nested. Sub-second precision is kept, with as many digits
+/// as needed out of 3, 6 or 9. Times outside years 0 to 9999 fail to
+/// serialize, since RFC 3339 can't represent them.
+///
+/// This type is only available with the `timestamp` feature enabled.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use serde_metaform::TimestampRfc3339;
+///
+/// #[derive(Serialize)]
+/// struct Event { at: TimestampRfc3339, seen: Vec<TimestampRfc3339> }
+///
+/// let at = UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+/// let event = Event {
+///     at: TimestampRfc3339(at),
+///     seen: vec![TimestampRfc3339(at + Duration::from_millis(250))],
+/// };
+/// assert_eq!(
+///     serde_metaform::to_string(&event).unwrap(),
+///     "at=2024-01-01T00%3A00%3A00Z&seen=%5B%222024-01-01T00%3A00%3A00.250Z%22%5D"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimestampRfc3339(pub SystemTime);
+
+impl Serialize for TimestampRfc3339 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match Rfc3339::new(self.0) {
+            Some(timestamp) => serializer.collect_str(&timestamp),
+            None => Err(S::Error::custom(
+                "timestamp is outside the years RFC 3339 can represent",
+            )),
+        }
+    }
+}
+
+/// The fields of a timestamp, in UTC.
+struct Rfc3339 {
+    year: i64,
+    month: u32,
+    day: u32,
+    seconds_of_day: u32,
+    nanos: u32,
+}
+
+impl Rfc3339 {
+    const SECONDS_PER_DAY: i64 = 86_400;
+
+    /// Splits `time` into its fields, or returns `None` if its year doesn't
+    /// have four digits.
+    fn new(time: SystemTime) -> Option<Self> {
+        let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => (i64::try_from(since.as_secs()).ok()?, since.subsec_nanos()),
+            Err(before) => {
+                let before = before.duration();
+                let secs = i64::try_from(before.as_secs()).ok()?;
+                match before.subsec_nanos() {
+                    0 => (-secs, 0),
+                    nanos => (-secs - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        let days = secs.div_euclid(Self::SECONDS_PER_DAY);
+        let seconds_of_day = secs.rem_euclid(Self::SECONDS_PER_DAY) as u32;
+        let (year, month, day) = civil_from_days(days);
+        (0..=9999).contains(&year).then_some(Self {
+            year,
+            month,
+            day,
+            seconds_of_day,
+            nanos,
+        })
+    }
+}
+
+impl fmt::Display for Rfc3339 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year,
+            self.month,
+            self.day,
+            self.seconds_of_day / 3600,
+            self.seconds_of_day / 60 % 60,
+            self.seconds_of_day % 60,
+        )?;
+        match self.nanos {
+            0 => {}
+            nanos if nanos % 1_000_000 == 0 => write!(f, ".{:03}", nanos / 1_000_000)?,
+            nanos if nanos % 1_000 == 0 => write!(f, ".{:06}", nanos / 1_000)?,
+            nanos => write!(f, ".{nanos:09}")?,
+        }
+        f.write_str("Z")
+    }
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian `(year, month, day)`.
+///
+/// This is Howard Hinnant's `civil_from_days`, which counts in 400-year eras
+/// starting on March 1st so that leap days fall at the end of each year.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::BTreeMap, time::Duration};
+
+    fn rfc3339(secs: i64, nanos: u32) -> String {
+        let time = if secs >= 0 {
+            UNIX_EPOCH + Duration::new(secs as u64, nanos)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+                + Duration::from_nanos(nanos.into())
+        };
+        Rfc3339::new(time).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(rfc3339(0, 0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_704_067_200, 0), "2024-01-01T00:00:00Z");
+        // Leap day, and the last second of the day.
+        assert_eq!(rfc3339(951_868_799, 0), "2000-02-29T23:59:59Z");
+        assert_eq!(rfc3339(4_107_542_400, 0), "2100-03-01T00:00:00Z");
+        assert_eq!(rfc3339(253_402_300_799, 0), "9999-12-31T23:59:59Z");
+
+        assert_eq!(rfc3339(0, 5_000_000), "1970-01-01T00:00:00.005Z");
+        assert_eq!(rfc3339(0, 5_000), "1970-01-01T00:00:00.000005Z");
+        assert_eq!(rfc3339(0, 5), "1970-01-01T00:00:00.000000005Z");
+    }
+
+    #[test]
+    fn test_before_epoch() {
+        assert_eq!(rfc3339(-1, 0), "1969-12-31T23:59:59Z");
+        assert_eq!(rfc3339(-1, 500_000_000), "1969-12-31T23:59:59.500Z");
+        assert_eq!(rfc3339(-86_400, 0), "1969-12-31T00:00:00Z");
+        assert_eq!(rfc3339(-62_167_219_200, 0), "0000-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let after = UNIX_EPOCH + Duration::from_secs(253_402_300_800);
+        assert!(Rfc3339::new(after).is_none());
+        let err = crate::to_string(&BTreeMap::from([("at", TimestampRfc3339(after))])).unwrap_err();
+        assert_eq!(
+            err.message(),
+            Some("timestamp is outside the years RFC 3339 can represent")
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let at = TimestampRfc3339(UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+        assert_eq!(
+            crate::FormEncoder::new()
+                .force_json_string_values(true)
+                .encode(&BTreeMap::from([("at", at)]))
+                .unwrap(),
+            "at=%222024-01-01T00%3A00%3A00Z%22"
+        );
+        assert_eq!(
+            serde_json::to_string(&at).unwrap(),
+            r#""2024-01-01T00:00:00Z""#
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! [`tower`](tower_service) middleware.
+//!
+//! This module is only available with the `tower` feature enabled. It provides
+//! [`MetaFormLayer`], which rewrites `application/json` request bodies into
+//! "Form + JSON" bodies before they reach the wrapped service. This lets a
+//! service that speaks JSON front an upstream API that only accepts this format.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{HeaderValue, Request, Response, StatusCode, header};
+use http_body::Body;
+use http_body_util::{BodyExt, Either, Full, LengthLimitError, Limited};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    CONTENT_TYPE, Serializer,
+    error::{Error, top_level_must_be_object},
+};
+
+/// The default limit on the size of a buffered JSON body: 2 MiB.
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// A [`Layer`] that transcodes JSON request bodies to "Form + JSON".
+///
+/// Requests whose `Content-Type` is `application/json` are buffered (up to the
+/// configured limit), transcoded, and forwarded with the `Content-Type` and
+/// `Content-Length` headers rewritten. Every other request is forwarded untouched.
+///
+/// The wrapped service receives an [`Either`] body: `Left` for untouched requests
+/// and `Right` for transcoded ones.
+///
+/// Requests that can't be transcoded never reach the wrapped service:
+///
+/// - A body over the limit is rejected with `413 Payload Too Large`.
+/// - A body that isn't valid JSON, or whose root isn't an object, is rejected
+///   with `400 Bad Request` and the error message as the body.
+#[derive(Debug, Clone, Copy)]
+pub struct MetaFormLayer {
+    limit: usize,
+}
+
+impl MetaFormLayer {
+    /// Creates a new layer with the [`DEFAULT_BODY_LIMIT`].
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+
+    /// Sets the maximum size, in bytes, of a JSON body that will be buffered.
+    #[inline]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for MetaFormLayer {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for MetaFormLayer {
+    type Service = MetaFormService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetaFormService {
+            inner,
+            limit: self.limit,
+        }
+    }
+}
+
+/// The [`Service`] created by [`MetaFormLayer`].
+#[derive(Debug, Clone)]
+pub struct MetaFormService<S> {
+    inner: S,
+    limit: usize,
+}
+
+impl<S, B, ResBody> Service<Request<B>> for MetaFormService<S>
+where
+    S: Service<Request<Either<B, Full<Bytes>>>, Response = Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    ResBody: From<String>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if !is_json(&req) {
+            return Box::pin(self.inner.call(req.map(Either::Left)));
+        }
+
+        // The clone is the one that was driven to readiness.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limit = self.limit;
+
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+
+            let json = match Limited::new(body, limit).collect().await {
+                Ok(collected) => collected.to_bytes(),
+                Err(err) if err.is::<LengthLimitError>() => {
+                    return Ok(reject(StatusCode::PAYLOAD_TOO_LARGE, err.to_string()));
+                }
+                Err(err) => return Ok(reject(StatusCode::BAD_REQUEST, err.to_string())),
+            };
+
+            let form = match transcode(&json) {
+                Ok(form) => Bytes::from(form),
+                Err(err) => return Ok(reject(StatusCode::BAD_REQUEST, err.to_string())),
+            };
+
+            // The new body has a known length. Keeping `Transfer-Encoding`
+            // alongside `Content-Length` would make the request invalid.
+            parts.headers.remove(header::TRANSFER_ENCODING);
+            parts
+                .headers
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE));
+            parts
+                .headers
+                .insert(header::CONTENT_LENGTH, HeaderValue::from(form.len()));
+
+            inner
+                .call(Request::from_parts(parts, Either::Right(Full::new(form))))
+                .await
+        })
+    }
+}
+
+/// Returns `true` if the request declares an `application/json` body.
+fn is_json<B>(req: &Request<B>) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+}
+
+/// Transcodes a JSON document into a "Form + JSON" body.
+fn transcode(json: &[u8]) -> Result<String, Error> {
+    // Reject other roots up front. Past this point, errors raised by the
+    // serializer are wrapped by the JSON parser and gain a position suffix.
+    let root = match json.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'{') | None => None,
+        Some(b'[') => Some("Seq"),
+        Some(b'"') => Some("str"),
+        Some(b't' | b'f') => Some("bool"),
+        Some(b'n') => Some("()"),
+        Some(_) => Some("number"),
+    };
+    if let Some(got) = root {
+        return Err(top_level_must_be_object(got));
+    }
+
+    let mut output = String::with_capacity(json.len());
+    let mut de = serde_json::Deserializer::from_slice(json);
+    let config = crate::Config {
+        // Transcoded values are read from the parser as they are serialized.
+        #[cfg(feature = "debug-verify")]
+        one_shot: true,
+        ..crate::Config::default()
+    };
+    serde_transcode::transcode(&mut de, Serializer::with_config(&mut output, config))?;
+    de.end().map_err(<Error as serde::ser::Error>::custom)?;
+    Ok(output)
+}
+
+fn reject<ResBody: From<String>>(status: StatusCode, message: String) -> Response<ResBody> {
+    let mut response = Response::new(ResBody::from(message));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::{ServiceBuilder, ServiceExt, service_fn};
+
+    /// Echoes the request's `Content-Type`, `Content-Length` and body back.
+    async fn echo(
+        req: Request<Either<String, Full<Bytes>>>,
+    ) -> Result<Response<String>, Infallible> {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .map(|value: &HeaderValue| value.to_str().unwrap().to_owned())
+                .unwrap_or_default()
+        };
+        let echoed = format!(
+            "{}|{}|",
+            header(header::CONTENT_TYPE),
+            header(header::CONTENT_LENGTH)
+        );
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        Ok(Response::new(echoed + std::str::from_utf8(&body).unwrap()))
+    }
+
+    async fn call(layer: MetaFormLayer, content_type: &str, body: &str) -> Response<String> {
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body.to_owned())
+            .unwrap();
+        ServiceBuilder::new()
+            .layer(layer)
+            .service(service_fn(echo))
+            .oneshot(req)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transcode() {
+        let response = call(
+            MetaFormLayer::new(),
+            "application/json; charset=utf-8",
+            r#"{"id": 7, "name": "John Doe", "tags": ["a", "b"]}"#,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.body(),
+            "application/x-www-form-urlencoded|49|id=7&name=John%20Doe&tags=%5B%22a%22%2C%22b%22%5D"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drops_transfer_encoding() {
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::TRANSFER_ENCODING, "chunked")
+            .body(r#"{"id": 7}"#.to_owned())
+            .unwrap();
+        let response = ServiceBuilder::new()
+            .layer(MetaFormLayer::new())
+            .service(service_fn(
+                |req: Request<Either<String, Full<Bytes>>>| async move {
+                    assert!(!req.headers().contains_key(header::TRANSFER_ENCODING));
+                    echo(req).await
+                },
+            ))
+            .oneshot(req)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "application/x-www-form-urlencoded|4|id=7");
+    }
+
+    #[tokio::test]
+    async fn test_passthrough() {
+        let response = call(MetaFormLayer::new(), "text/plain", "{}").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "text/plain||{}");
+    }
+
+    #[tokio::test]
+    async fn test_non_object_root() {
+        let response = call(MetaFormLayer::new(), "application/json", "[1, 2]").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.body(),
+            "Top-level value must be a struct or map, but got Seq"
+        );
+
+        let response = call(MetaFormLayer::new(), "application/json", "{} trailing").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_over_limit() {
+        let response = call(
+            MetaFormLayer::new().limit(8),
+            "application/json",
+            r#"{"id": 12345}"#,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,196 @@
+//! Tracing of the pairs a [`Serializer`](crate::Serializer) writes.
+//!
+//! This module is only available with the `tracing` feature enabled. Each pair
+//! is reported with a `trace!` event holding its decoded key and the length of
+//! its encoded value, and each map or struct with a `debug!` summary. Values
+//! themselves are never recorded, since they may hold secrets.
+
+use alloc::string::String;
+
+use serde::Serialize;
+
+use crate::{json::KeySerializerNoQuotes, write::Plain};
+
+/// The tracing state of a serializer.
+#[derive(Debug, Default)]
+pub(crate) struct PairTrace {
+    /// Whether `debug!` events were enabled when the serializer was created.
+    /// Nothing is recorded otherwise.
+    pub(crate) on: bool,
+    /// The decoded key of the pair being written.
+    key: String,
+    /// The bytes written for the key of the pair being written, including the
+    /// `&` and the key-value separator.
+    pub(crate) key_len: usize,
+    /// The bytes written for every pair so far.
+    bytes: usize,
+}
+
+impl PairTrace {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self {
+            on: tracing::enabled!(tracing::Level::DEBUG),
+            ..Self::default()
+        }
+    }
+
+    /// Records the decoded text of the next key.
+    pub(crate) fn set_key<K>(&mut self, key: &K)
+    where
+        K: ?Sized + Serialize,
+    {
+        self.key.clear();
+        // A key that fails is reported when it is written.
+        let _ = key.serialize(KeySerializerNoQuotes {
+            output: Plain::new(&mut self.key),
+        });
+    }
+
+    /// Records the decoded text of the next key, given already encoded.
+    pub(crate) fn set_raw_key(&mut self, raw_key: &str) {
+        self.key.clear();
+        self.key
+            .push_str(&percent_encoding::percent_decode_str(raw_key).decode_utf8_lossy());
+    }
+
+    /// Reports a pair whose value took `value_len` bytes.
+    pub(crate) fn pair(&mut self, value_len: usize) {
+        self.bytes += self.key_len + value_len;
+        tracing::trace!(key = %self.key, value_len, "pair written");
+    }
+
+    /// Reports the payload written so far, made of `pairs` pairs.
+    #[inline]
+    pub(crate) fn finish(&self, pairs: usize) {
+        if self.on {
+            tracing::debug!(pairs, bytes = self.bytes, "form written");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Serializer;
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        Event, Subscriber,
+        field::{Field, Visit},
+    };
+    use tracing_subscriber::{
+        Layer,
+        layer::{Context, SubscriberExt},
+    };
+
+    /// Collects every event as its level, message and other fields.
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for Events {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            struct Fields(String);
+
+            impl Visit for Fields {
+                fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+                    if !self.0.is_empty() {
+                        self.0.push(' ');
+                    }
+                    self.0.push_str(&format!("{}={value:?}", field.name()));
+                }
+            }
+
+            let mut fields = Fields(event.metadata().level().to_string());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+    }
+
+    fn traced(f: impl FnOnce()) -> Vec<String> {
+        let events = Events::default();
+        let subscriber = tracing_subscriber::registry().with(events.clone());
+        tracing::subscriber::with_default(subscriber, f);
+        events.0.lock().unwrap().clone()
+    }
+
+    #[derive(Serialize)]
+    struct Message {
+        #[serde(rename = "to user")]
+        to: &'static str,
+        tags: Vec<&'static str>,
+    }
+
+    #[test]
+    fn test_pairs_are_traced() {
+        let message = Message {
+            to: "a b",
+            tags: vec!["x"],
+        };
+        let events = traced(|| {
+            assert_eq!(
+                crate::to_string(&message).unwrap(),
+                "to%20user=a%20b&tags=%5B%22x%22%5D"
+            );
+        });
+        assert_eq!(
+            events,
+            [
+                "TRACE message=pair written key=to user value_len=5",
+                "TRACE message=pair written key=tags value_len=13",
+                "DEBUG message=form written pairs=2 bytes=34",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modes_are_traced() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        let events = traced(|| {
+            for transactional in [false, true] {
+                let mut out = String::new();
+                let mut map = Serializer::new(&mut out)
+                    .transactional(transactional)
+                    .serialize_map(None)
+                    .unwrap();
+                map.serialize_entry("a", &1).unwrap();
+                map.serialize_raw_pair("b%20c", &[1, 2]).unwrap();
+                map.end().unwrap();
+                assert_eq!(out, "a=1&b%20c=%5B1%2C2%5D");
+            }
+
+            let encoded = crate::FormEncoder::new()
+                .flat_strict(true)
+                .encode(&BTreeMap::from([("a b", Some("c d")), ("e", None)]))
+                .unwrap();
+            assert_eq!(encoded, "a+b=c+d");
+        });
+        let pairs = [
+            "TRACE message=pair written key=a value_len=1",
+            "TRACE message=pair written key=b c value_len=11",
+            "DEBUG message=form written pairs=2 bytes=21",
+        ];
+        assert_eq!(events[..3], pairs);
+        assert_eq!(events[3..6], pairs);
+        assert_eq!(
+            events[6..],
+            [
+                "TRACE message=pair written key=a b value_len=3",
+                "DEBUG message=form written pairs=1 bytes=7",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nothing_is_traced_when_disabled() {
+        // No subscriber: the state stays empty, and nothing is recorded.
+        let mut out = String::new();
+        let ser = Serializer::new(&mut out);
+        assert!(!ser.trace.on);
+        BTreeMap::from([("a", 1)]).serialize(ser).unwrap();
+        assert_eq!(out, "a=1");
+    }
+}
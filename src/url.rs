@@ -0,0 +1,110 @@
+//! [`form_urlencoded`] interop.
+//!
+//! This module is only available with the `url` feature enabled. It lets
+//! "Form + JSON" pairs be added to a query string or body that is otherwise
+//! built with `form_urlencoded::Serializer` (also re-exported as
+//! `url::form_urlencoded::Serializer`).
+
+use form_urlencoded::Target;
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// Appends the pairs of `value` to a `form_urlencoded::Serializer`.
+///
+/// Each key and its JSON value text is handed to
+/// [`append_pair`](form_urlencoded::Serializer::append_pair), so the encoding
+/// is left to `form_urlencoded`. The result decodes to the same pairs as
+/// [`to_string`](crate::to_string), but the bytes differ:
+///
+/// - `form_urlencoded` writes spaces as `+`, where this crate writes `%20`.
+/// - `form_urlencoded` leaves `*` unescaped, where this crate writes `%2A`.
+///
+/// A server that decodes the body as a regular form sees identical values.
+///
+/// # Errors
+///
+/// Returns an error if `value` can't be serialized. See [`to_writer`](crate::to_writer)
+/// for details. Nothing is appended to `target` in that case.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Serialize;
+/// use serde_metaform::url::extend_form_urlencoded;
+///
+/// #[derive(Serialize)]
+/// struct Batch { ids: Vec<u32> }
+///
+/// let mut query = form_urlencoded::Serializer::new(String::new());
+/// query.append_pair("access_token", "abc");
+/// extend_form_urlencoded(&mut query, &Batch { ids: vec![1, 2] }).unwrap();
+///
+/// assert_eq!(query.finish(), "access_token=abc&ids=%5B1%2C2%5D");
+/// ```
+pub fn extend_form_urlencoded<T, U>(
+    target: &mut form_urlencoded::Serializer<'_, U>,
+    value: &T,
+) -> Result<(), Error>
+where
+    T: ?Sized + Serialize,
+    U: Target,
+{
+    // Our encoding writes `+` as `%2B`, so decoding it back is lossless.
+    let encoded = crate::to_string(value)?;
+    target.extend_pairs(form_urlencoded::parse(encoded.as_bytes()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Payload {
+        text: &'static str,
+        nested: Nested,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        a: &'static str,
+    }
+
+    #[test]
+    fn test_mixed_builder() {
+        let payload = Payload {
+            text: "1 + 1 = 2",
+            nested: Nested { a: "x y" },
+        };
+
+        let mut builder = form_urlencoded::Serializer::new(String::new());
+        builder.append_pair("access_token", "a b");
+        extend_form_urlencoded(&mut builder, &payload).unwrap();
+        builder.append_pair("after", "*");
+        let encoded = builder.finish();
+
+        assert_eq!(
+            encoded,
+            "access_token=a+b&text=1+%2B+1+%3D+2&nested=%7B%22a%22%3A%22x+y%22%7D&after=*"
+        );
+
+        let pairs: Vec<_> = form_urlencoded::parse(encoded.as_bytes())
+            .into_owned()
+            .collect();
+        let expected = [
+            ("access_token", "a b"),
+            ("text", "1 + 1 = 2"),
+            ("nested", r#"{"a":"x y"}"#),
+            ("after", "*"),
+        ];
+        assert_eq!(pairs, expected.map(|(k, v)| (k.to_owned(), v.to_owned())));
+    }
+
+    #[test]
+    fn test_error_appends_nothing() {
+        let mut builder = form_urlencoded::Serializer::new(String::new());
+        extend_form_urlencoded(&mut builder, &[1, 2]).unwrap_err();
+        assert_eq!(builder.finish(), "");
+    }
+}
@@ -0,0 +1,140 @@
+//! Allocation-free validation of "Form + JSON" payloads.
+
+use percent_encoding::percent_decode_str;
+
+/// Checks whether `s` is a well-formed "Form + JSON" payload.
+///
+/// This is a pure check that never allocates. It returns `true` only if:
+///
+/// 1. every `%` starts a valid escape (`%XX`, where `XX` are hex digits),
+/// 2. every `&`-separated pair contains exactly one `=`, and
+/// 3. every key and value percent-decodes to valid UTF-8.
+///
+/// The empty string is valid: it is what an empty struct serializes to.
+///
+/// Note that values aren't required to parse as JSON. Top-level strings are
+/// written bare (`name=John%20Doe`, not `name=%22John%20Doe%22`), so any
+/// decoded text is a valid value.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_metaform::is_valid_form_string;
+///
+/// assert!(is_valid_form_string("id=1&tags=%5B%22a%22%5D"));
+/// assert!(!is_valid_form_string("id=1&tags"));
+/// assert!(!is_valid_form_string("id=%ZZ"));
+/// ```
+pub fn is_valid_form_string(s: &str) -> bool {
+    s.is_empty()
+        || s.split('&').all(|pair| {
+            let mut parts = pair.split('=');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(key), Some(value), None) => {
+                    is_valid_component(key) && is_valid_component(value)
+                }
+                _ => false,
+            }
+        })
+}
+
+#[inline]
+pub(crate) fn is_valid_component(s: &str) -> bool {
+    has_valid_escapes(s) && decodes_to_utf8(s)
+}
+
+/// Returns `true` if every `%` in `s` is followed by two hex digits.
+fn has_valid_escapes(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while let Some(offset) = bytes[i..].iter().position(|&b| b == b'%') {
+        let at = i + offset;
+        match bytes.get(at + 1..at + 3) {
+            Some([hi, lo]) if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => i = at + 3,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Returns `true` if `s` percent-decodes to valid UTF-8.
+///
+/// Decoding goes through a small stack buffer that is validated whenever it
+/// fills up. A multi-byte character cut off at the end of the buffer is carried
+/// over to the next round.
+fn decodes_to_utf8(s: &str) -> bool {
+    // Without escapes, the decoded component is `s` itself.
+    if !s.contains('%') {
+        return true;
+    }
+
+    let mut buf = [0u8; 64];
+    let mut len = 0;
+    for byte in percent_decode_str(s) {
+        buf[len] = byte;
+        len += 1;
+        if len == buf.len() {
+            match core::str::from_utf8(&buf) {
+                Ok(_) => len = 0,
+                // Incomplete character at the end, carry it over.
+                Err(err) if err.error_len().is_none() => {
+                    let valid = err.valid_up_to();
+                    buf.copy_within(valid.., 0);
+                    len = buf.len() - valid;
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+    core::str::from_utf8(&buf[..len]).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid() {
+        assert!(is_valid_form_string(""));
+        assert!(is_valid_form_string("id=123&name=John%20Doe"));
+        assert!(is_valid_form_string("empty="));
+        assert!(is_valid_form_string(
+            "field=%7B%22recipient%22%3A%22Victor%20%2B%20Sons%22%7D"
+        ));
+        // Multi-byte characters: "é" and "😀".
+        assert!(is_valid_form_string("name=%C3%A9&emoji=%F0%9F%98%80"));
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        assert!(!is_valid_form_string("a=%"));
+        assert!(!is_valid_form_string("a=%2"));
+        assert!(!is_valid_form_string("a=%G0"));
+        assert!(!is_valid_form_string("%zz=1"));
+    }
+
+    #[test]
+    fn test_invalid_pairs() {
+        assert!(!is_valid_form_string("a"));
+        assert!(!is_valid_form_string("a=1&"));
+        assert!(!is_valid_form_string("a=1&&b=2"));
+        assert!(!is_valid_form_string("a=1=2"));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // Lone continuation byte.
+        assert!(!is_valid_form_string("a=%80"));
+        // Truncated two-byte sequence.
+        assert!(!is_valid_form_string("a=%C3"));
+        assert!(!is_valid_form_string("%FF=1"));
+    }
+
+    #[test]
+    fn test_long_values() {
+        // Multi-byte characters straddling the decode buffer boundary.
+        let value = "%C3%A9".repeat(100);
+        assert!(is_valid_form_string(&format!("a=x{value}")));
+        assert!(!is_valid_form_string(&format!("a=x{value}%C3")));
+    }
+}
@@ -0,0 +1,156 @@
+//! Cross-checking nested JSON against `serde_json`.
+//!
+//! This module is only compiled with the `debug-verify` feature, which is meant
+//! for test and staging builds. Every value written as JSON is serialized a
+//! second time, and the JSON text, before percent-encoding, is compared with
+//! what `serde_json` writes for the same value. A mismatch panics with both
+//! texts.
+//!
+//! Options that change the JSON on purpose (`bool_as_int`, and the
+//! `bigdecimal` adapter's number literals) are turned off for the comparison,
+//! so only the JSON emission itself is checked.
+
+use serde::Serialize;
+
+use crate::{Config, json::JsonSerializer, write::PercentEncoding};
+
+/// Panics if this crate and `serde_json` write different JSON for `value`.
+///
+/// Values that either side fails to serialize are skipped: the error is
+/// reported by the real serialization. So are values from a [`Config`] marked
+/// `one_shot`, such as those fed by `serde_transcode`.
+pub(crate) fn check<T>(value: &T, config: Config)
+where
+    T: ?Sized + Serialize,
+{
+    if config.one_shot {
+        return;
+    }
+    let Ok(expected) = serde_json::to_string(value) else {
+        return;
+    };
+
+    let mut encoded = String::with_capacity(expected.len());
+    let serialized = value.serialize(JsonSerializer {
+        output: PercentEncoding::new(&mut encoded),
+        is_top_level_value: false,
+        config: Config {
+            verifying: true,
+            ..Config::default()
+        },
+        depth: 0,
+    });
+    if serialized.is_err() {
+        return;
+    }
+
+    let actual = percent_encoding::percent_decode_str(&encoded).decode_utf8_lossy();
+    assert!(
+        actual == expected,
+        "JSON mismatch with serde_json\n  serde-metaform: {actual}\n      serde_json: {expected}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn check_default<T: ?Sized + Serialize>(value: &T) {
+        check(value, Config::default());
+    }
+
+    #[test]
+    fn test_floats() {
+        check_default(&[
+            0.1,
+            -0.0,
+            1e300,
+            1e-300,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            123456789.0,
+        ]);
+        check_default(&[0.1f32, 1e38, 16777216.0]);
+        check_default(&[f64::NAN, f64::INFINITY, f64::NEG_INFINITY]);
+    }
+
+    #[test]
+    fn test_unicode_escapes() {
+        let control: String = (0u8..0x20).map(char::from).collect();
+        check_default(&control);
+        check_default(&"\u{7f} \u{2028} \u{2029} caf\u{e9} \u{1F600} \"\\/");
+        check_default(&BTreeMap::from([("key\n\"quoted\"", '\u{10FFFF}')]));
+    }
+
+    #[test]
+    fn test_big_integers() {
+        check_default(&(u64::MAX, i64::MIN, u128::MAX, i128::MIN));
+        check_default(&BTreeMap::from([(u128::MAX, 1), (0, 2)]));
+        check_default(&BTreeMap::from([(i128::MIN, 1)]));
+    }
+
+    #[test]
+    fn test_through_serializer() {
+        #[derive(Serialize)]
+        enum Shape {
+            Circle { radius: f64 },
+            Line(f32, f32),
+        }
+
+        #[derive(Serialize)]
+        struct Payload {
+            shapes: Vec<Shape>,
+            line: Shape,
+            circle: Shape,
+            flags: BTreeMap<bool, Option<()>>,
+        }
+
+        // Every value is checked on the way.
+        crate::to_string(&Payload {
+            shapes: vec![Shape::Circle { radius: 0.5 }, Shape::Line(1e-7, 3.0)],
+            line: Shape::Line(0.25, -1.0),
+            circle: Shape::Circle { radius: 2.0 },
+            flags: BTreeMap::from([(true, None), (false, Some(()))]),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "JSON mismatch with serde_json")]
+    fn test_mismatch_panics() {
+        /// Serializes differently for `serde_json`.
+        struct Divergent;
+
+        impl Serialize for Divergent {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if std::any::type_name::<S>().contains("serde_json") {
+                    serializer.serialize_u8(1)
+                } else {
+                    serializer.serialize_u8(2)
+                }
+            }
+        }
+
+        check_default(&Divergent);
+    }
+
+    #[test]
+    fn test_one_shot_skipped() {
+        /// Panics if serialized more than once.
+        struct Once(std::cell::Cell<bool>);
+
+        impl Serialize for Once {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                assert!(!self.0.replace(true), "serialized twice");
+                serializer.serialize_u8(1)
+            }
+        }
+
+        let config = Config {
+            one_shot: true,
+            ..Config::default()
+        };
+        check(&Once(Default::default()), config);
+    }
+}
@@ -3,15 +3,18 @@
 //! percent-encoding for URL query strings.
 //!
 //! It features:
-//! - A core `WWrite` trait for writing primitives like booleans, integers, floats, etc.
+//! - A core `WWritePrimitives` trait for writing primitives like booleans, integers, floats, etc.,
+//!   and `WWrite`, for the writers that can also be escaped.
 //! - `PercentEncoding`, a writer that percent-encodes all string data written to it.
 //! - `EscapingPercentEncodingWrite`, a writer that first applies JSON-style string
 //!   escaping and then percent-encodes the result.
 
-use std::fmt::Write;
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
 
 use itoa::Integer;
-use json_escape::token::{EscapedToken, escape_str};
+#[cfg(feature = "alloc")]
 use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
 use ryu::Float;
 
@@ -20,7 +23,7 @@ macro_rules! w_const_chars {
         paste::paste! {
             $(
                 #[inline]
-                fn [<write_ $name:lower>](&mut self) -> std::fmt::Result {
+                fn [<write_ $name:lower>](&mut self) -> core::fmt::Result {
                     self.write_str($literal)
                 }
             )*
@@ -32,16 +35,19 @@ macro_rules! w_const_chars {
 /// This trait extends `std::fmt::Write` with methods for writing primitives
 /// like booleans, numbers, and byte arrays, along with common structural
 /// characters used in formats like JSON or query strings (e.g., `:`, `,`, `[`).
-pub(crate) trait WWrite: Write {
+///
+/// Escaped writers only implement this trait, not [`WWrite`], so they can't be
+/// escaped a second time by accident.
+pub(crate) trait WWritePrimitives: Write {
     /// Writes the string "null" to the underlying writer.
     #[inline]
-    fn write_null(&mut self) -> std::fmt::Result {
+    fn write_null(&mut self) -> core::fmt::Result {
         self.write_str("null")
     }
 
     /// Writes a boolean value as "true" or "false".
     #[inline]
-    fn write_bool(&mut self, value: bool) -> std::fmt::Result {
+    fn write_bool(&mut self, value: bool) -> core::fmt::Result {
         if value {
             self.write_str("true")
         } else {
@@ -50,8 +56,12 @@ pub(crate) trait WWrite: Write {
     }
 
     /// Writes any integer type that implements `itoa::Integer`.
+    ///
+    /// `itoa::Buffer` and `ryu::Buffer` are uninitialized stack arrays, so
+    /// creating one per call costs nothing. Sharing them across a serialization
+    /// would only add indirection (see the "Numeric Arrays" benchmark).
     #[inline]
-    fn write_integer<I: Integer>(&mut self, value: I) -> std::fmt::Result {
+    fn write_integer<I: Integer>(&mut self, value: I) -> core::fmt::Result {
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
         self.write_str(s)
@@ -65,40 +75,80 @@ pub(crate) trait WWrite: Write {
     /// undefined behavior. The caller **must** ensure the value is finite
     /// before calling this method (e.g., by checking `value.is_finite()`).
     #[inline]
-    fn write_float<F: Float>(&mut self, value: F) -> std::fmt::Result {
+    fn write_float<F: Float>(&mut self, value: F) -> core::fmt::Result {
         let mut buffer = ryu::Buffer::new();
         let s = buffer.format_finite(value);
         self.write_str(s)
     }
 
+    /// Writes a value's `Display` output.
+    ///
+    /// `Display` impls tend to emit their output in many small pieces, and
+    /// each piece would go through the encoder on its own. Instead, the pieces
+    /// are collected in a stack buffer, so that output that fits (UUIDs,
+    /// timestamps, IP addresses) is written with a single `write_str` call.
+    /// Longer output is flushed to the writer as the buffer fills up.
+    #[inline]
+    fn write_display<T>(&mut self, value: &T) -> core::fmt::Result
+    where
+        T: ?Sized + core::fmt::Display,
+        Self: Sized,
+    {
+        let mut buffered = StackBuffered {
+            inner: self,
+            buf: [0; STACK_BUFFER_SIZE],
+            len: 0,
+        };
+        write!(buffered, "{value}")?;
+        buffered.flush()
+    }
+
     /// Writes a slice of bytes as a comma-separated list of numbers enclosed
     /// in square brackets (e.g., `[1,2,3]`).
+    ///
+    /// The numbers and commas are formatted in chunks, so a large slice takes
+    /// a few `write_str` calls rather than two per byte.
     #[inline]
-    fn write_byte_array(&mut self, value: &[u8]) -> std::fmt::Result {
+    fn write_byte_array(&mut self, value: &[u8]) -> core::fmt::Result {
         self.write_left_sq_bracket()?;
-        let mut is_first = true;
-        value.iter().try_for_each(|b| {
-            if !is_first {
-                self.write_comma()?
-            }
-            self.write_integer(*b)?;
-            is_first = false;
-            Ok(())
-        })?;
+        write_byte_chunks(value, ",", |chunk| self.write_str(chunk))?;
         self.write_right_sq_bracket()
     }
 
+    /// Records that a JSON array or object opens `depth` levels deep.
+    ///
+    /// Only [`PercentEncoding`] keeps track, for
+    /// [`to_string_with_stats`](crate::to_string_with_stats). Wrappers pass it
+    /// on, and other writers ignore it.
+    #[inline]
+    fn nested(&mut self, depth: usize) {
+        let _ = depth;
+    }
+
+    w_const_chars! {
+        colon ":";
+        quote "\"";
+        comma ",";
+        left_bracket "{";
+        right_bracket "}";
+        left_sq_bracket "[";
+        right_sq_bracket "]";
+    }
+}
+
+/// A [`WWritePrimitives`] writer that can be escaped.
+pub(crate) trait WWrite: WWritePrimitives {
     /// Returns a new writer that applies format-specific string escaping.
     ///
     /// This method wraps the current writer in a new writer that performs
     /// JSON-style escaping on any string data written to it.
     ///
-    /// **Note**: This operation is not idempotent. Calling `escape()`
-    /// multiple times will result in multiple layers of escaping wrappers,
-    /// which can lead to unexpected behavior or, in extreme cases of recursive
-    /// type definitions, a compiler stack overflow.
+    /// The returned writer only implements [`WWritePrimitives`], so it can't be
+    /// escaped again: that would escape the escapes and, with recursive types,
+    /// can overflow the compiler's trait evaluation. Writers that really need a
+    /// second level, like [`Stringify`], apply it themselves.
     #[inline]
-    fn escape(&mut self) -> impl WWrite
+    fn escape(&mut self) -> impl WWritePrimitives
     where
         Self: Sized,
     {
@@ -106,12 +156,12 @@ pub(crate) trait WWrite: Write {
 
         impl<W: WWrite> Write for Escape<'_, W> {
             #[inline]
-            fn write_str(&mut self, s: &str) -> std::fmt::Result {
-                write!(self.0, "{}", escape_str(s))
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                write_json_escaped(self.0, s)
             }
         }
 
-        impl<W: WWrite> WWrite for Escape<'_, W> {}
+        impl<W: WWrite> WWritePrimitives for Escape<'_, W> {}
 
         Escape(self)
     }
@@ -143,6 +193,7 @@ pub(crate) trait WWrite: Write {
     /// This is safe because the `AsMut` struct is a `#[repr(transparent)]`
     /// newtype wrapper around `W`, guaranteeing that a pointer to `AsMut<W>`
     /// has the same layout and ABI as a pointer to `W`.
+    #[cfg(not(feature = "dyn-write"))]
     #[inline]
     fn as_mut(&mut self) -> impl WWrite
     where
@@ -153,7 +204,7 @@ pub(crate) trait WWrite: Write {
 
         impl<W: WWrite> Write for &mut AsMut<W> {
             #[inline]
-            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
                 self.0.write_str(s)
             }
         }
@@ -163,7 +214,7 @@ pub(crate) trait WWrite: Write {
                 paste::paste! {
                     $(
                         #[inline]
-                        fn [<write_ $name:lower>](&mut self) -> std::fmt::Result {
+                        fn [<write_ $name:lower>](&mut self) -> core::fmt::Result {
                             self.0.[<write_ $name:lower>]()
                         }
                     )*
@@ -171,32 +222,45 @@ pub(crate) trait WWrite: Write {
             }
         }
 
-        impl<W: WWrite> WWrite for &mut AsMut<W> {
+        impl<W: WWrite> WWritePrimitives for &mut AsMut<W> {
             #[inline]
-            fn write_null(&mut self) -> std::fmt::Result {
+            fn write_null(&mut self) -> core::fmt::Result {
                 self.0.write_null()
             }
 
             #[inline]
-            fn write_bool(&mut self, value: bool) -> std::fmt::Result {
+            fn write_bool(&mut self, value: bool) -> core::fmt::Result {
                 self.0.write_bool(value)
             }
 
             #[inline]
-            fn write_integer<I: Integer>(&mut self, value: I) -> std::fmt::Result {
+            fn write_integer<I: Integer>(&mut self, value: I) -> core::fmt::Result {
                 self.0.write_integer(value)
             }
 
             #[inline]
-            fn write_float<F: Float>(&mut self, value: F) -> std::fmt::Result {
+            fn write_float<F: Float>(&mut self, value: F) -> core::fmt::Result {
                 self.0.write_float(value)
             }
 
             #[inline]
-            fn write_byte_array(&mut self, value: &[u8]) -> std::fmt::Result {
+            fn write_byte_array(&mut self, value: &[u8]) -> core::fmt::Result {
                 self.0.write_byte_array(value)
             }
 
+            #[inline]
+            fn nested(&mut self, depth: usize) {
+                self.0.nested(depth)
+            }
+
+            w_mut_const_chars! {
+                colon quote comma
+                left_bracket right_bracket
+                left_sq_bracket right_sq_bracket
+            }
+        }
+
+        impl<W: WWrite> WWrite for &mut AsMut<W> {
             #[inline]
             fn as_mut(&mut self) -> impl WWrite {
                 // Stop the recursive type nesting by returning the current wrapper.
@@ -205,28 +269,27 @@ pub(crate) trait WWrite: Write {
             }
 
             #[inline]
-            fn escape(&mut self) -> impl WWrite {
+            fn escape(&mut self) -> impl WWritePrimitives {
                 self.0.escape()
             }
-
-            w_mut_const_chars! {
-                colon quote comma
-                left_bracket right_bracket
-                left_sq_bracket right_sq_bracket
-            }
         }
 
-        unsafe { std::mem::transmute::<&mut Self, &mut AsMut<Self>>(self) }
+        unsafe { core::mem::transmute::<&mut Self, &mut AsMut<Self>>(self) }
     }
 
-    w_const_chars! {
-        colon ":";
-        quote "\"";
-        comma ",";
-        left_bracket "{";
-        right_bracket "}";
-        left_sq_bracket "[";
-        right_sq_bracket "]";
+    /// Returns a type-erased handle to this writer.
+    ///
+    /// With the `dyn-write` feature, every nested value is serialized through a
+    /// [`DynWrite`] instead of a chain of generic wrappers. This also stops the
+    /// recursion described above, and means each `Serialize` impl is compiled
+    /// once for all writers rather than once per writer type.
+    #[cfg(feature = "dyn-write")]
+    #[inline]
+    fn as_mut(&mut self) -> impl WWrite
+    where
+        Self: Sized,
+    {
+        DynWrite::new(self)
     }
 }
 
@@ -235,7 +298,7 @@ macro_rules! const_chars {
         paste::paste! {
             $(
                 #[inline]
-                fn [<write_ $name:lower>](&mut self) -> std::fmt::Result {
+                fn [<write_ $name:lower>](&mut self) -> core::fmt::Result {
                     const [<$name:upper>]: &str = $encoding;
 
                     self.w.write_str([<$name:upper>])
@@ -250,31 +313,166 @@ macro_rules! const_chars {
 /// This set defines which characters should be percent-encoded. According to
 /// RFC 3986, alphanumeric characters and `*-._~` are considered "unreserved"
 /// and do not require encoding. This set includes all other characters.
+///
+/// The encoder itself is hand-written (see `is_unreserved`); this set is the
+/// reference it is tested against.
+#[cfg(all(test, feature = "alloc"))]
 const FORM_URLENCODING_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
     .remove(b'-')
     .remove(b'.')
     .remove(b'_')
     .remove(b'~');
 
+/// The `AsciiSet` of characters not allowed in an RFC 3986 query component.
+///
+/// A query may contain unreserved characters (`A-Z a-z 0-9 - . _ ~`),
+/// sub-delimiters (`! $ & ' ( ) * + , ; =`), `:`, `@`, `/` and `?`. Everything
+/// else, including `%` itself and `#`, must be encoded.
+#[cfg(feature = "alloc")]
+pub(crate) const RFC3986_QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'/')
+    .remove(b'?');
+
+/// The `AsciiSet` of characters that must be encoded in an RFC 5987 `value-chars`.
+///
+/// [RFC 5987, section 3.2.1](https://www.rfc-editor.org/rfc/rfc5987#section-3.2.1):
+/// `attr-char` is `ALPHA / DIGIT / "!" / "#" / "$" / "&" / "+" / "-" / "." /
+/// "^" / "_" / "`" / "|" / "~"`. Everything else is percent-encoded.
+#[cfg(feature = "alloc")]
+pub(crate) const RFC5987_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
 /// A writer that percent-encodes string data.
 ///
 /// This struct wraps another writer (`W`) and encodes any string written to it
 /// using the `FORM_URLENCODING_ENCODE_SET`. Primitives like numbers and booleans
 /// are written directly without encoding, as they are already URL-safe.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use serde_metaform::PercentEncoding;
+///
+/// let mut out = String::new();
+/// PercentEncoding::new(&mut out).write_str("a b&c").unwrap();
+/// assert_eq!(out, "a%20b%26c");
+/// ```
 #[derive(Debug)]
-pub(crate) struct PercentEncoding<W> {
+pub struct PercentEncoding<W> {
     w: W,
+    /// The deepest JSON array or object written through this writer.
+    max_depth: usize,
 }
 
 impl<W> PercentEncoding<W> {
     /// Creates a new `PercentEncoding` writer wrapping `w`.
     #[inline(always)]
     pub fn new(w: W) -> Self {
-        Self { w }
+        Self { w, max_depth: 0 }
+    }
+
+    /// Returns the deepest JSON array or object written so far: `0` if none
+    /// was, `1` for a top-level one, and so on.
+    #[inline]
+    pub(crate) fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Unwraps the writer, returning the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+}
+
+impl<W: Write> PercentEncoding<W> {
+    /// Writes pre-split chunks as one logical string.
+    ///
+    /// This is for strings that are stored in pieces, like ropes. Each chunk goes
+    /// through the same encoding as [`write_str`](Write::write_str), and since
+    /// percent-encoding works on whole characters, the output is the same as
+    /// writing the joined string, without joining it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_metaform::PercentEncoding;
+    ///
+    /// let mut encoded = PercentEncoding::new(String::new());
+    /// encoded.write_str_chunks(["a b", "", "&c"]).unwrap();
+    /// assert_eq!(encoded.into_inner(), "a%20b%26c");
+    /// ```
+    #[inline]
+    pub fn write_str_chunks<I, S>(&mut self, chunks: I) -> core::fmt::Result
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        write_chunks(self, chunks)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PercentEncoding<String> {
+    /// Returns the encoded text written so far.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::fmt::Write;
+    /// use serde_metaform::PercentEncoding;
+    ///
+    /// let mut encoded = PercentEncoding::new(String::new());
+    /// assert!(encoded.is_empty());
+    /// encoded.write_str("a b").unwrap();
+    /// assert_eq!(encoded.as_str(), "a%20b");
+    /// assert_eq!(encoded.len(), 5);
+    /// ```
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.w
+    }
+
+    /// Returns the length of the encoded text written so far, in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.w.len()
     }
 
-    /// The percent-encoded representation of a double quote (`"`).
-    const QUOTE: &'static str = "%22";
+    /// Returns `true` if nothing has been written yet.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.w.is_empty()
+    }
 }
 
 impl<W> Write for PercentEncoding<W>
@@ -282,22 +480,256 @@ where
     W: Write,
 {
     #[inline(always)]
-    fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        let mut encoded = percent_encoding::utf8_percent_encode(s, FORM_URLENCODING_ENCODE_SET);
-        encoded.try_for_each(|s| self.w.write_str(s))?;
-        Ok(())
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write_encoded(&mut self.w, s.as_bytes(), &PERCENT_ENCODE)
+    }
+
+    /// Encodes the character's bytes straight from the table, skipping the
+    /// prefix scan and batching of `write_str`.
+    #[inline]
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        let mut utf8 = [0; 4];
+        let mut buf = [0; 4 * MAX_ENCODED_LEN];
+        let mut len = 0;
+        for &byte in c.encode_utf8(&mut utf8).as_bytes() {
+            let encoded = &PERCENT_ENCODE[usize::from(byte)];
+            buf[len..len + MAX_ENCODED_LEN].copy_from_slice(&encoded.bytes);
+            len += usize::from(encoded.len);
+        }
+        self.w.write_str(ascii(&buf[..len]))
     }
 }
 
-impl<W: Write> WWrite for PercentEncoding<W> {
+/// The size of the stack buffer used by [`write_encoded`].
+const BATCH_SIZE: usize = 128;
+
+/// The longest output of a single byte: `%5Cu001f`.
+const MAX_ENCODED_LEN: usize = 8;
+
+/// Returns `true` if `byte` is left as is by `FORM_URLENCODING_ENCODE_SET`.
+#[inline(always)]
+const fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// The output of a single byte in an [`EncodeTable`].
+#[derive(Debug, Clone, Copy)]
+struct Encoded {
+    /// The output, padded to a fixed size so it can be copied in one go.
+    bytes: [u8; MAX_ENCODED_LEN],
+    len: u8,
+}
+
+impl Encoded {
+    const fn new(s: &str) -> Self {
+        let mut bytes = [0; MAX_ENCODED_LEN];
+        let mut i = 0;
+        while i < s.len() {
+            bytes[i] = s.as_bytes()[i];
+            i += 1;
+        }
+        Self {
+            bytes,
+            len: s.len() as u8,
+        }
+    }
+}
+
+/// Maps every byte to its output.
+type EncodeTable = [Encoded; 256];
+
+const fn encode_table(json_escape: bool) -> EncodeTable {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    // The escapes `json_escape` produces: short forms where JSON has them, and
+    // lowercase `\uXXXX` for the other control characters.
+    const CONTROL: [&str; 0x20] = [
+        "%5Cu0000", "%5Cu0001", "%5Cu0002", "%5Cu0003", "%5Cu0004", "%5Cu0005", "%5Cu0006",
+        "%5Cu0007", "%5Cb", "%5Ct", "%5Cn", "%5Cu000b", "%5Cf", "%5Cr", "%5Cu000e", "%5Cu000f",
+        "%5Cu0010", "%5Cu0011", "%5Cu0012", "%5Cu0013", "%5Cu0014", "%5Cu0015", "%5Cu0016",
+        "%5Cu0017", "%5Cu0018", "%5Cu0019", "%5Cu001a", "%5Cu001b", "%5Cu001c", "%5Cu001d",
+        "%5Cu001e", "%5Cu001f",
+    ];
+
+    let mut table = [Encoded {
+        bytes: [0; MAX_ENCODED_LEN],
+        len: 0,
+    }; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as u8;
+        table[i] = if is_unreserved(byte) {
+            let mut bytes = [0; MAX_ENCODED_LEN];
+            bytes[0] = byte;
+            Encoded { bytes, len: 1 }
+        } else if json_escape && byte < 0x20 {
+            Encoded::new(CONTROL[i])
+        } else if json_escape && byte == b'"' {
+            Encoded::new("%5C%22")
+        } else if json_escape && byte == b'\\' {
+            Encoded::new("%5C%5C")
+        } else {
+            let mut bytes = [0; MAX_ENCODED_LEN];
+            bytes[0] = b'%';
+            bytes[1] = HEX[(byte >> 4) as usize];
+            bytes[2] = HEX[(byte & 0xF) as usize];
+            Encoded { bytes, len: 3 }
+        };
+        i += 1;
+    }
+    table
+}
+
+/// Returns `true` if every output in `table` is ASCII.
+const fn is_ascii_table(table: &EncodeTable) -> bool {
+    let mut i = 0;
+    while i < table.len() {
+        let encoded = &table[i];
+        let mut j = 0;
+        while j < encoded.len as usize {
+            if !encoded.bytes[j].is_ascii() {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+// `ascii` and `AsciiBytes` rely on the tables never writing anything else.
+const _: () = assert!(is_ascii_table(&encode_table(false)) && is_ascii_table(&encode_table(true)));
+
+/// The table used by [`PercentEncoding`].
+static PERCENT_ENCODE: EncodeTable = encode_table(false);
+
+/// The table used by [`EscapingPercentEncodingWrite`]: bytes that JSON escapes
+/// are escaped, then percent-encoded.
+static JSON_ESCAPE_PERCENT_ENCODE: EncodeTable = encode_table(true);
+
+/// Encodes `bytes` into `w` in a single pass, as directed by `table`.
+///
+/// `percent_encoding`'s encoder yields one piece per unencoded run and one per
+/// escaped byte, so text with scattered reserved characters turns into many tiny
+/// writes. When the target is a `fmt::Formatter`, each of those is a virtual
+/// call. Instead, each byte's output is looked up and appended to a stack
+/// buffer that is flushed in chunks.
+#[inline]
+fn write_encoded<W: Write>(w: &mut W, bytes: &[u8], table: &EncodeTable) -> core::fmt::Result {
+    // Both tables copy exactly the unreserved bytes.
+    let start = unreserved_prefix_len(bytes);
+    if start == bytes.len() {
+        // Fast path: nothing needs encoding.
+        return w.write_str(ascii(bytes));
+    }
+    if start != 0 {
+        w.write_str(ascii(&bytes[..start]))?;
+    }
+
+    let mut buf = [0u8; BATCH_SIZE];
+    let mut len = 0;
+    for &byte in &bytes[start..] {
+        if len > BATCH_SIZE - MAX_ENCODED_LEN {
+            w.write_str(ascii(&buf[..len]))?;
+            len = 0;
+        }
+        let encoded = &table[usize::from(byte)];
+        buf[len..len + MAX_ENCODED_LEN].copy_from_slice(&encoded.bytes);
+        len += usize::from(encoded.len);
+    }
+    w.write_str(ascii(&buf[..len]))
+}
+
+/// Returns the length of the longest prefix of `bytes` that needs no encoding.
+///
+/// Most keys and many values (IDs, enum names) need no encoding at all. Whole
+/// chunks are checked with [`is_unreserved_branchless`], which the compiler
+/// vectorizes. Only the chunk holding the first byte to encode is scanned byte
+/// by byte, and the encoder resumes right there.
+#[inline]
+fn unreserved_prefix_len(bytes: &[u8]) -> usize {
+    const CHUNK: usize = 16;
+
+    let mut len = 0;
+    for chunk in bytes.chunks_exact(CHUNK) {
+        let mut all = true;
+        for &byte in chunk {
+            all &= is_unreserved_branchless(byte);
+        }
+        if !all {
+            break;
+        }
+        len += CHUNK;
+    }
+    len + bytes[len..]
+        .iter()
+        .position(|&b| !is_unreserved(b))
+        .unwrap_or(bytes.len() - len)
+}
+
+/// Same as [`is_unreserved`], without short-circuiting.
+#[inline(always)]
+fn is_unreserved_branchless(byte: u8) -> bool {
+    ((byte | 0x20).wrapping_sub(b'a') < 26)
+        | (byte.wrapping_sub(b'0') < 10)
+        | (byte == b'-')
+        | (byte == b'.')
+        | (byte == b'_')
+        | (byte == b'~')
+}
+
+/// Writes `s` with JSON string escaping, without the surrounding quotes.
+///
+/// Uses the same escapes as the encode tables: short forms where JSON has
+/// them, and lowercase `\uXXXX` for the other control characters. Runs of
+/// bytes that need no escaping are written in one call.
+fn write_json_escaped<W: Write>(w: &mut W, s: &str) -> core::fmt::Result {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        let mut unicode = *b"\\u00XX";
+        let escaped = match byte {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            0x08 => "\\b",
+            b'\t' => "\\t",
+            b'\n' => "\\n",
+            0x0C => "\\f",
+            b'\r' => "\\r",
+            0..0x20 => {
+                unicode[4] = HEX[usize::from(byte >> 4)];
+                unicode[5] = HEX[usize::from(byte & 0xF)];
+                ascii(&unicode)
+            }
+            _ => continue,
+        };
+        // `i` is at an ASCII byte, so both slices are on char boundaries.
+        w.write_str(&s[start..i])?;
+        w.write_str(escaped)?;
+        start = i + 1;
+    }
+    w.write_str(&s[start..])
+}
+
+/// Views bytes that are known to be ASCII as a `&str`.
+#[inline(always)]
+fn ascii(bytes: &[u8]) -> &str {
+    debug_assert!(bytes.is_ascii());
+    // SAFETY: Only unreserved ASCII bytes, `%XX` escapes, JSON `\u00XX`
+    // escapes, digits and commas are ever passed here, so the bytes are ASCII and thus valid UTF-8.
+    unsafe { core::str::from_utf8_unchecked(bytes) }
+}
+
+impl<W: Write> WWritePrimitives for PercentEncoding<W> {
     #[inline]
-    fn write_null(&mut self) -> std::fmt::Result {
+    fn write_null(&mut self) -> core::fmt::Result {
         // ENCODING: Needs no encoding.
         self.w.write_str("null")
     }
 
     #[inline]
-    fn write_bool(&mut self, value: bool) -> std::fmt::Result {
+    fn write_bool(&mut self, value: bool) -> core::fmt::Result {
         // ENCODING: Needs no encoding.
         if value {
             self.w.write_str("true")
@@ -307,13 +739,26 @@ impl<W: Write> WWrite for PercentEncoding<W> {
     }
 
     #[inline]
-    fn write_integer<I: Integer>(&mut self, value: I) -> std::fmt::Result {
+    fn write_integer<I: Integer>(&mut self, value: I) -> core::fmt::Result {
         let mut buffer = itoa::Buffer::new();
         let s = buffer.format(value);
         // ENCODING: Needs no encoding. It only produces 0-9 and -
         self.w.write_str(s)
     }
 
+    #[inline]
+    fn write_byte_array(&mut self, value: &[u8]) -> core::fmt::Result {
+        self.write_left_sq_bracket()?;
+        // ENCODING: Digits need no encoding, and the comma is pre-encoded.
+        write_byte_chunks(value, "%2C", |chunk| self.w.write_str(chunk))?;
+        self.write_right_sq_bracket()
+    }
+
+    #[inline]
+    fn nested(&mut self, depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+    }
+
     const_chars! {
         colon "%3A" ":";
         quote "%22" "\"";
@@ -323,22 +768,23 @@ impl<W: Write> WWrite for PercentEncoding<W> {
         left_sq_bracket "%5B" "[";
         right_sq_bracket "%5D" "]";
     }
+}
 
+impl<W: Write> WWrite for PercentEncoding<W> {
     #[inline]
-    fn escape(&mut self) -> impl WWrite {
-        EscapingPercentEncodingWrite { inner: self }
+    fn escape(&mut self) -> impl WWritePrimitives {
+        EscapingPercentEncodingWrite::new(self)
     }
 }
 
+#[cfg(feature = "std")]
 impl<W> std::io::Write for PercentEncoding<W>
 where
     W: Write,
 {
     #[inline(always)]
     fn write(&mut self, b: &[u8]) -> std::io::Result<usize> {
-        let mut encoded = percent_encoding::percent_encode(b, FORM_URLENCODING_ENCODE_SET);
-        encoded
-            .try_for_each(|s| self.w.write_str(s))
+        write_encoded(&mut self.w, b, &PERCENT_ENCODE)
             .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
         Ok(b.len())
     }
@@ -348,54 +794,124 @@ where
     }
 }
 
+/// A writer that JSON-escapes like [`WWrite::escape`], and also writes every
+/// non-ASCII character as a `\uXXXX` escape.
+///
+/// Characters above U+FFFF become a UTF-16 surrogate pair, as in JSON. The
+/// escapes are written to the wrapped writer unescaped, so its own encoding
+/// still applies: through [`PercentEncoding`], `é` becomes `%5Cu00e9`.
+pub(crate) struct UnicodeEscape<'a, W> {
+    inner: &'a mut W,
+}
+
+impl<'a, W> UnicodeEscape<'a, W> {
+    /// Creates a new `UnicodeEscape` writing to `inner`.
+    #[inline]
+    pub fn new(inner: &'a mut W) -> Self {
+        Self { inner }
+    }
+}
+
+impl<W: WWrite> Write for UnicodeEscape<'_, W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+
+        let mut rest = s;
+        while let Some(i) = rest.bytes().position(|b| !b.is_ascii()) {
+            self.inner.escape().write_str(&rest[..i])?;
+            let c = rest[i..].chars().next().unwrap_or_default();
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                let mut escape = *b"\\u0000";
+                for (digit, shift) in escape[2..].iter_mut().zip([12, 8, 4, 0]) {
+                    *digit = HEX[usize::from((*unit >> shift) & 0xF)];
+                }
+                self.inner.write_str(ascii(&escape))?;
+            }
+            rest = &rest[i + c.len_utf8()..];
+        }
+        self.inner.escape().write_str(rest)
+    }
+}
+
+impl<W: WWrite> WWritePrimitives for UnicodeEscape<'_, W> {}
+
 /// A writer that first applies JSON-style string escaping and then percent-encodes the result.
 ///
 /// This is useful for serializing string values that are themselves expected
 /// to be valid JSON strings, but embedded within a URL. For example, writing
 /// the string `a"b\c` would result in `a%5C%22b%5C%5Cc`.
+///
+/// Both steps happen in one pass over the string.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use serde_metaform::{EscapingPercentEncodingWrite, PercentEncoding};
+///
+/// let mut out = String::new();
+/// let mut encoding = PercentEncoding::new(&mut out);
+/// EscapingPercentEncodingWrite::new(&mut encoding).write_str("a\"b\\c").unwrap();
+/// assert_eq!(out, "a%5C%22b%5C%5Cc");
+/// ```
 #[derive(Debug)]
-pub(crate) struct EscapingPercentEncodingWrite<'a, W> {
+pub struct EscapingPercentEncodingWrite<'a, W> {
     inner: &'a mut PercentEncoding<W>,
 }
 
+impl<'a, W: Write> EscapingPercentEncodingWrite<'a, W> {
+    /// Creates a new `EscapingPercentEncodingWrite` writing through `inner`.
+    #[inline]
+    pub fn new(inner: &'a mut PercentEncoding<W>) -> Self {
+        Self { inner }
+    }
+
+    /// Writes pre-split chunks as one logical JSON string body.
+    ///
+    /// Like [`PercentEncoding::write_str_chunks`], but each chunk is also
+    /// JSON-escaped. Escapes never span characters, so the output is the same
+    /// as writing the joined string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_metaform::{EscapingPercentEncodingWrite, PercentEncoding};
+    ///
+    /// let mut encoded = PercentEncoding::new(String::new());
+    /// EscapingPercentEncodingWrite::new(&mut encoded)
+    ///     .write_str_chunks(["say \"hi", "\""])
+    ///     .unwrap();
+    /// assert_eq!(encoded.into_inner(), "say%20%5C%22hi%5C%22");
+    /// ```
+    #[inline]
+    pub fn write_str_chunks<I, S>(&mut self, chunks: I) -> core::fmt::Result
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        write_chunks(self, chunks)
+    }
+}
+
+/// Writes each chunk in turn with `write_str`.
+#[inline]
+fn write_chunks<I, S>(w: &mut impl Write, chunks: I) -> core::fmt::Result
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    chunks
+        .into_iter()
+        .try_for_each(|chunk| w.write_str(chunk.as_ref()))
+}
+
 impl<W> Write for EscapingPercentEncodingWrite<'_, W>
 where
     W: Write,
 {
     #[inline]
-    fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        let mut escaped = escape_str(s);
-        escaped.try_for_each(|escaped_token| {
-            match escaped_token {
-                EscapedToken::Literal(literal) => {
-                    // This literal part is already safe from JSON's perspective,
-                    // but still needs percent-encoding.
-                    self.inner.write_str(literal)
-                }
-                EscapedToken::Escaped(escaped) => {
-                    const REVERSE_SOLIDUS: &str = "%5C";
-
-                    self.inner.w.write_str(REVERSE_SOLIDUS)?;
-
-                    match &escaped[1..] {
-                        "\"" => self.inner.w.write_str(PercentEncoding::<W>::QUOTE),
-                        "\\" => self.inner.w.write_str(REVERSE_SOLIDUS),
-                        // `json_escape` doesn't escape '/', but we handle it defensively.
-                        "/" => {
-                            const SOLIDUS: &str = "%2F";
-
-                            self.inner.w.write_str(SOLIDUS)
-                        }
-                        other =>
-                        // ENCODING: Other JSON escapes like '\b', '\f', '\n', '\r', '\t'
-                        // are valid in the URL set and do not need further encoding.
-                        {
-                            self.inner.w.write_str(other)
-                        }
-                    }
-                }
-            }
-        })
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write_encoded(&mut self.inner.w, s.as_bytes(), &JSON_ESCAPE_PERCENT_ENCODE)
     }
 }
 
@@ -404,7 +920,7 @@ macro_rules! w_ep_const_chars {
         paste::paste! {
             $(
                 #[inline]
-                fn [<write_ $name:lower>](&mut self) -> std::fmt::Result {
+                fn [<write_ $name:lower>](&mut self) -> core::fmt::Result {
                     self.inner.[<write_ $name:lower>]()
                 }
             )*
@@ -412,30 +928,30 @@ macro_rules! w_ep_const_chars {
     }
 }
 
-impl<W: Write> WWrite for EscapingPercentEncodingWrite<'_, W> {
+impl<W: Write> WWritePrimitives for EscapingPercentEncodingWrite<'_, W> {
     #[inline]
-    fn write_null(&mut self) -> std::fmt::Result {
+    fn write_null(&mut self) -> core::fmt::Result {
         // Primitives are not JSON-escaped.
         self.inner.write_null()
     }
 
     #[inline]
-    fn write_bool(&mut self, value: bool) -> std::fmt::Result {
+    fn write_bool(&mut self, value: bool) -> core::fmt::Result {
         self.inner.write_bool(value)
     }
 
     #[inline]
-    fn write_integer<I: Integer>(&mut self, value: I) -> std::fmt::Result {
+    fn write_integer<I: Integer>(&mut self, value: I) -> core::fmt::Result {
         self.inner.write_integer(value)
     }
 
     #[inline]
-    fn write_float<F: Float>(&mut self, value: F) -> std::fmt::Result {
+    fn write_float<F: Float>(&mut self, value: F) -> core::fmt::Result {
         self.inner.write_float(value)
     }
 
     #[inline]
-    fn write_byte_array(&mut self, value: &[u8]) -> std::fmt::Result {
+    fn write_byte_array(&mut self, value: &[u8]) -> core::fmt::Result {
         self.inner.write_byte_array(value)
     }
 
@@ -444,61 +960,676 @@ impl<W: Write> WWrite for EscapingPercentEncodingWrite<'_, W> {
         left_bracket right_bracket
         left_sq_bracket right_sq_bracket
     }
-    // We shouldn't prevent escape on escape like we did before. If it's a bug,
-    // let's catch it.
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// An object-safe mirror of [`WWrite`], used by [`DynWrite`].
+///
+/// `WWrite` can't be made into a trait object: numbers are written through
+/// generic methods, and `escape` returns a new writer. Here numbers are passed
+/// pre-formatted, and every method takes an `escaped` flag that routes the call
+/// through `escape()` on the concrete writer.
+#[cfg(feature = "dyn-write")]
+pub(crate) trait WWriteDyn {
+    fn dyn_write_str(&mut self, s: &str, escaped: bool) -> core::fmt::Result;
 
-    #[test]
-    fn trait_evaluation_overflow() {
-        use std::collections::HashMap;
-        // ISSUE:
-        //     error[E0275]: overflow evaluating the requirement `&mut std::string::String: std::fmt::Write`
-        //     |
-        //     = help: consider increasing the recursion limit by adding a `#![recursion_limit = "256"]` attribute to your crate (`serde_metaform`)
-        //     = note: required for `&mut &mut std::string::String` to implement `std::fmt::Write`
-        //     = note: 126 redundant requirements hidden
-        //     = note: required for `&mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut ...` to implement `std::fmt::Write`
-        // note: required for `EscapingPercentEncodingWrite<&mut &mut &mut &mut &mut &mut &mut &mut &mut ...>` to implement `write::WWrite`
-        //    --> src/write.rs:278:16
-        //     |
-        // 278 | impl<W: Write> WWrite for EscapingPercentEncodingWrite<W> {
-        //     |         -----  ^^^^^^     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
-        //     |         |
-        //     |         unsatisfied trait bound introduced here
-        // note: required for `KeySerializerNoQuotes<EscapingPercentEncodingWrite<&mut &mut &mut &mut ...>>` to implement `tests::_::_serde::Serializer`
-        //    --> src/json.rs:562:17
-        //     |
-        // 562 | impl<W: WWrite> ser::Serializer for KeySerializerNoQuotes<W> {
-        //     |         ------  ^^^^^^^^^^^^^^^     ^^^^^^^^^^^^^^^^^^^^^^^^
-        //     |         |
-        //     |         unsatisfied trait bound introduced here
-        //
-        // In the former version, I pulled a trick to avoid work and it resulted in this (even without the trick, we'd get this)
-        // The original issue was discovered with serde_json::Value whose sheer existence is statically recursive.
-        let _ = crate::to_string(&serde_json::json!(()));
+    fn dyn_write_null(&mut self, escaped: bool) -> core::fmt::Result;
 
-        // Adding this incase to make it clearer what this is.
-        #[derive(serde::Serialize)]
-        struct NestedSerialize {
-            value: HashMap<String, NestedSerialize>,
-        }
+    fn dyn_write_bool(&mut self, value: bool, escaped: bool) -> core::fmt::Result;
 
-        let _ = crate::to_string(&NestedSerialize {
-            value: HashMap::new(),
-        });
-    }
+    /// Writes a formatted integer or finite float.
+    fn dyn_write_number(&mut self, number: &str, escaped: bool) -> core::fmt::Result;
 
-    /// Tests the `PercentEncoding` writer.
-    #[test]
-    fn test_percent_encoding_writer() {
-        let mut buf = String::new();
-        let mut writer = PercentEncoding::new(&mut buf);
+    fn dyn_write_colon(&mut self, escaped: bool) -> core::fmt::Result;
 
-        writer.write_str("hello world").unwrap();
+    fn dyn_write_quote(&mut self, escaped: bool) -> core::fmt::Result;
+
+    fn dyn_write_comma(&mut self, escaped: bool) -> core::fmt::Result;
+
+    fn dyn_write_left_bracket(&mut self, escaped: bool) -> core::fmt::Result;
+
+    fn dyn_write_right_bracket(&mut self, escaped: bool) -> core::fmt::Result;
+
+    fn dyn_write_left_sq_bracket(&mut self, escaped: bool) -> core::fmt::Result;
+
+    fn dyn_write_right_sq_bracket(&mut self, escaped: bool) -> core::fmt::Result;
+
+    fn dyn_nested(&mut self, depth: usize);
+}
+
+/// Calls `$method` on `$self`, or on `$self.escape()` if `$escaped` is set.
+#[cfg(feature = "dyn-write")]
+macro_rules! maybe_escaped {
+    ($self:ident, $escaped:ident, $method:ident($($arg:expr),*)) => {
+        if $escaped {
+            $self.escape().$method($($arg),*)
+        } else {
+            $self.$method($($arg),*)
+        }
+    };
+}
+
+#[cfg(feature = "dyn-write")]
+macro_rules! dyn_const_chars {
+    ($($name:ident)*) => {
+        paste::paste! {
+            $(
+                #[inline]
+                fn [<dyn_write_ $name>](&mut self, escaped: bool) -> core::fmt::Result {
+                    maybe_escaped!(self, escaped, [<write_ $name>]())
+                }
+            )*
+        }
+    }
+}
+
+#[cfg(feature = "dyn-write")]
+impl<T: WWrite> WWriteDyn for T {
+    #[inline]
+    fn dyn_write_str(&mut self, s: &str, escaped: bool) -> core::fmt::Result {
+        maybe_escaped!(self, escaped, write_str(s))
+    }
+
+    #[inline]
+    fn dyn_write_null(&mut self, escaped: bool) -> core::fmt::Result {
+        maybe_escaped!(self, escaped, write_null())
+    }
+
+    #[inline]
+    fn dyn_write_bool(&mut self, value: bool, escaped: bool) -> core::fmt::Result {
+        maybe_escaped!(self, escaped, write_bool(value))
+    }
+
+    #[inline]
+    fn dyn_write_number(&mut self, number: &str, escaped: bool) -> core::fmt::Result {
+        // ENCODING: Numbers are made of characters that no writer changes, so
+        // they can go through `write_str`.
+        maybe_escaped!(self, escaped, write_str(number))
+    }
+
+    #[inline]
+    fn dyn_nested(&mut self, depth: usize) {
+        self.nested(depth)
+    }
+
+    dyn_const_chars! {
+        colon quote comma
+        left_bracket right_bracket
+        left_sq_bracket right_sq_bracket
+    }
+}
+
+/// A type-erased [`WWrite`], see [`WWrite::as_mut`].
+///
+/// `DynWrite` is the only writer type nested values see, whatever the writer at
+/// the top. Its `as_mut` and `escape` return another `DynWrite` rather than a
+/// new wrapper type.
+#[cfg(feature = "dyn-write")]
+pub(crate) struct DynWrite<'a> {
+    inner: &'a mut dyn WWriteDyn,
+    /// Whether this writer was escaped. Passed along with every call.
+    escaped: bool,
+}
+
+#[cfg(feature = "dyn-write")]
+impl<'a> DynWrite<'a> {
+    #[inline]
+    pub fn new(inner: &'a mut dyn WWriteDyn) -> Self {
+        Self {
+            inner,
+            escaped: false,
+        }
+    }
+}
+
+#[cfg(feature = "dyn-write")]
+impl Write for DynWrite<'_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.inner.dyn_write_str(s, self.escaped)
+    }
+}
+
+#[cfg(feature = "dyn-write")]
+macro_rules! w_dyn_const_chars {
+    ($($name:ident)*) => {
+        paste::paste! {
+            $(
+                #[inline]
+                fn [<write_ $name>](&mut self) -> core::fmt::Result {
+                    self.inner.[<dyn_write_ $name>](self.escaped)
+                }
+            )*
+        }
+    }
+}
+
+#[cfg(feature = "dyn-write")]
+impl WWritePrimitives for DynWrite<'_> {
+    #[inline]
+    fn write_null(&mut self) -> core::fmt::Result {
+        self.inner.dyn_write_null(self.escaped)
+    }
+
+    #[inline]
+    fn write_bool(&mut self, value: bool) -> core::fmt::Result {
+        self.inner.dyn_write_bool(value, self.escaped)
+    }
+
+    #[inline]
+    fn write_integer<I: Integer>(&mut self, value: I) -> core::fmt::Result {
+        let mut buffer = itoa::Buffer::new();
+        self.inner
+            .dyn_write_number(buffer.format(value), self.escaped)
+    }
+
+    #[inline]
+    fn write_float<F: Float>(&mut self, value: F) -> core::fmt::Result {
+        let mut buffer = ryu::Buffer::new();
+        self.inner
+            .dyn_write_number(buffer.format_finite(value), self.escaped)
+    }
+
+    #[inline]
+    fn nested(&mut self, depth: usize) {
+        self.inner.dyn_nested(depth)
+    }
+
+    w_dyn_const_chars! {
+        colon quote comma
+        left_bracket right_bracket
+        left_sq_bracket right_sq_bracket
+    }
+}
+
+#[cfg(feature = "dyn-write")]
+impl WWrite for DynWrite<'_> {
+    #[inline]
+    fn escape(&mut self) -> impl WWritePrimitives {
+        DynWrite {
+            inner: &mut *self.inner,
+            escaped: true,
+        }
+    }
+
+    #[inline]
+    fn as_mut(&mut self) -> impl WWrite {
+        // Reborrow instead of adding a layer of indirection per nesting level.
+        DynWrite {
+            inner: &mut *self.inner,
+            escaped: self.escaped,
+        }
+    }
+}
+
+/// The size of the chunks written by [`WWrite::write_byte_array`].
+const BYTE_CHUNK_SIZE: usize = 4096;
+
+/// The decimal digits of every byte value, padded to three bytes.
+struct Digits {
+    bytes: [u8; 3],
+    len: u8,
+}
+
+static DIGITS: [Digits; 256] = {
+    let mut table = [const {
+        Digits {
+            bytes: [0; 3],
+            len: 0,
+        }
+    }; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = i as u8;
+        table[i] = if byte >= 100 {
+            Digits {
+                bytes: [b'0' + byte / 100, b'0' + byte / 10 % 10, b'0' + byte % 10],
+                len: 3,
+            }
+        } else if byte >= 10 {
+            Digits {
+                bytes: [b'0' + byte / 10, b'0' + byte % 10, 0],
+                len: 2,
+            }
+        } else {
+            Digits {
+                bytes: [b'0' + byte, 0, 0],
+                len: 1,
+            }
+        };
+        i += 1;
+    }
+    table
+};
+
+/// Formats `value` as comma-separated numbers and passes the result to
+/// `write` in chunks of up to [`BYTE_CHUNK_SIZE`] bytes.
+///
+/// `comma` is the separator as the writer would output it, so writers that
+/// encode commas can pass the chunks on without encoding them again.
+#[inline]
+fn write_byte_chunks(
+    value: &[u8],
+    comma: &str,
+    mut write: impl FnMut(&str) -> core::fmt::Result,
+) -> core::fmt::Result {
+    let comma = comma.as_bytes();
+    let mut buf = [0u8; BYTE_CHUNK_SIZE];
+    let mut len = 0;
+    let Some((&first, rest)) = value.split_first() else {
+        return Ok(());
+    };
+    let digits = &DIGITS[usize::from(first)];
+    buf[..3].copy_from_slice(&digits.bytes);
+    len += usize::from(digits.len);
+    for &byte in rest {
+        // Room for a comma and all three digits, which are always copied.
+        if len > BYTE_CHUNK_SIZE - comma.len() - 3 {
+            write(ascii(&buf[..len]))?;
+            len = 0;
+        }
+        buf[len..len + comma.len()].copy_from_slice(comma);
+        len += comma.len();
+        let digits = &DIGITS[usize::from(byte)];
+        buf[len..len + 3].copy_from_slice(&digits.bytes);
+        len += usize::from(digits.len);
+    }
+    write(ascii(&buf[..len]))
+}
+
+/// The size of the buffer used by [`WWrite::write_display`].
+const STACK_BUFFER_SIZE: usize = 64;
+
+/// Collects small writes on the stack before passing them on to `inner`.
+struct StackBuffered<'a, W> {
+    inner: &'a mut W,
+    buf: [u8; STACK_BUFFER_SIZE],
+    len: usize,
+}
+
+impl<W: Write> StackBuffered<'_, W> {
+    #[inline]
+    fn flush(&mut self) -> core::fmt::Result {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let len = core::mem::take(&mut self.len);
+        // SAFETY: The buffer is only ever filled with whole `&str`s.
+        let s = unsafe { core::str::from_utf8_unchecked(&self.buf[..len]) };
+        self.inner.write_str(s)
+    }
+}
+
+impl<W: Write> Write for StackBuffered<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if s.len() > STACK_BUFFER_SIZE - self.len {
+            self.flush()?;
+            if s.len() > STACK_BUFFER_SIZE {
+                return self.inner.write_str(s);
+            }
+        }
+        self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+/// A writer that only counts the bytes written to it.
+#[derive(Debug, Default)]
+pub(crate) struct Counter {
+    len: usize,
+}
+
+impl Counter {
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Write for Counter {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+/// A writer that appends to a byte buffer.
+///
+/// The serializer only ever emits ASCII, so the bytes of each chunk are
+/// appended as they are, with no `String` in between.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub(crate) struct AsciiBytes<'a> {
+    bytes: &'a mut Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> AsciiBytes<'a> {
+    /// Creates a new `AsciiBytes` writer appending to `bytes`.
+    #[inline]
+    pub fn new(bytes: &'a mut Vec<u8>) -> Self {
+        Self { bytes }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for AsciiBytes<'_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        debug_assert!(s.is_ascii(), "non-ASCII output: {s:?}");
+        self.bytes.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// A writer that counts the bytes written through it.
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+pub(crate) struct Counting<W> {
+    inner: W,
+    count: usize,
+}
+
+#[cfg(feature = "tracing")]
+impl<W> Counting<W> {
+    /// Creates a new `Counting` writer wrapping `inner`.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<W: Write> Write for Counting<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.count += s.len();
+        self.inner.write_str(s)
+    }
+}
+
+/// A writer that either writes through, or holds everything back until
+/// [`commit`](Self::commit).
+///
+/// Top-level enum variants write their pair through this, so that in
+/// [transactional](crate::Serializer::transactional) mode a variant that fails
+/// part-way writes nothing.
+pub(crate) enum Pending<W> {
+    Direct(W),
+    #[cfg(feature = "alloc")]
+    Held(W, String),
+}
+
+impl<W: Write> Pending<W> {
+    /// Creates a `Pending` writer that holds its output back if `hold` is set.
+    #[inline]
+    pub fn new(writer: W, hold: bool) -> Self {
+        #[cfg(feature = "alloc")]
+        if hold {
+            return Self::Held(writer, String::new());
+        }
+        let _ = hold;
+        Self::Direct(writer)
+    }
+
+    /// Writes out everything held back.
+    #[inline]
+    pub fn commit(self) -> core::fmt::Result {
+        match self {
+            Self::Direct(_) => Ok(()),
+            #[cfg(feature = "alloc")]
+            Self::Held(mut writer, held) => writer.write_str(&held),
+        }
+    }
+}
+
+impl<W: Write> Write for Pending<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        match self {
+            Self::Direct(writer) => writer.write_str(s),
+            #[cfg(feature = "alloc")]
+            Self::Held(_, held) => held.write_str(s),
+        }
+    }
+}
+
+/// A writer that fills a caller-provided byte slice.
+///
+/// Writing never fails. Once the slice is full, the remaining output is only
+/// counted, so the caller learns how large the slice needed to be.
+#[derive(Debug)]
+pub(crate) struct SliceWrite<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWrite<'a> {
+    /// Creates a new `SliceWrite` writing to the start of `buf`.
+    #[inline]
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Returns the number of bytes written so far, including those that
+    /// didn't fit.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the size of the slice.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+impl Write for SliceWrite<'_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if let Some(dest) = self.buf.get_mut(self.len..end) {
+            dest.copy_from_slice(s.as_bytes());
+        }
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// A writer that passes everything through unchanged.
+///
+/// All `WWrite` methods use their default, unencoded implementations. This is
+/// useful for rendering keys or JSON text into a plain buffer.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub(crate) struct Plain<W> {
+    inner: W,
+}
+
+#[cfg(feature = "alloc")]
+impl<W> Plain<W> {
+    /// Creates a new `Plain` writer wrapping `inner`.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<W: Write> Write for Plain<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.inner.write_str(s)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<W: Write> WWritePrimitives for Plain<W> {}
+
+#[cfg(feature = "alloc")]
+impl<W: Write> WWrite for Plain<W> {}
+
+/// The set `application/x-www-form-urlencoded` encoders leave alone besides
+/// alphanumerics, as used by `form_urlencoded` and `serde_urlencoded`.
+#[cfg(feature = "alloc")]
+const URLENCODED_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'*')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_');
+
+/// Writes `s` the way `application/x-www-form-urlencoded` encoders do.
+///
+/// This differs from [`PercentEncoding`] in two ways: spaces become `+`, and
+/// `*` is left as is while `~` is encoded.
+#[cfg(feature = "alloc")]
+pub(crate) fn write_urlencoded<W: Write>(w: &mut W, s: &str) -> core::fmt::Result {
+    for (i, part) in s.split(' ').enumerate() {
+        if i != 0 {
+            w.write_char('+')?;
+        }
+        percent_encoding::utf8_percent_encode(part, URLENCODED_ENCODE_SET)
+            .try_for_each(|s| w.write_str(s))?;
+    }
+    Ok(())
+}
+
+/// A writer that turns everything written to it into the contents of a JSON string.
+///
+/// Unlike [`WWrite::escape`], structural characters are escaped too, so a JSON
+/// document written through `Stringify` becomes a valid JSON string body once it
+/// is wrapped in quotes. For example, `{"a":1}` is written as `{\"a\":1}`.
+#[derive(Debug)]
+pub(crate) struct Stringify<W> {
+    inner: W,
+}
+
+impl<W> Stringify<W> {
+    /// Creates a new `Stringify` writer wrapping `inner`.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Unwraps the writer, returning the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Returns a reference to the underlying writer.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+}
+
+impl<W: WWrite> Write for Stringify<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.inner.escape().write_str(s)
+    }
+}
+
+impl<W: WWrite> WWritePrimitives for Stringify<W> {
+    #[inline]
+    fn write_null(&mut self) -> core::fmt::Result {
+        // Primitives never need escaping.
+        self.inner.write_null()
+    }
+
+    #[inline]
+    fn write_byte_array(&mut self, value: &[u8]) -> core::fmt::Result {
+        self.inner.write_byte_array(value)
+    }
+
+    #[inline]
+    fn write_bool(&mut self, value: bool) -> core::fmt::Result {
+        self.inner.write_bool(value)
+    }
+
+    #[inline]
+    fn write_integer<I: Integer>(&mut self, value: I) -> core::fmt::Result {
+        self.inner.write_integer(value)
+    }
+
+    #[inline]
+    fn write_float<F: Float>(&mut self, value: F) -> core::fmt::Result {
+        self.inner.write_float(value)
+    }
+
+    #[inline]
+    fn nested(&mut self, depth: usize) {
+        self.inner.nested(depth)
+    }
+}
+
+impl<W: WWrite> WWrite for Stringify<W> {}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use json_escape::token::escape_str;
+    use std::prelude::rust_2024::*;
+
+    #[test]
+    fn trait_evaluation_overflow() {
+        use std::collections::HashMap;
+        // ISSUE:
+        //     error[E0275]: overflow evaluating the requirement `&mut std::string::String: std::fmt::Write`
+        //     |
+        //     = help: consider increasing the recursion limit by adding a `#![recursion_limit = "256"]` attribute to your crate (`serde_metaform`)
+        //     = note: required for `&mut &mut std::string::String` to implement `std::fmt::Write`
+        //     = note: 126 redundant requirements hidden
+        //     = note: required for `&mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut &mut ...` to implement `std::fmt::Write`
+        // note: required for `EscapingPercentEncodingWrite<&mut &mut &mut &mut &mut &mut &mut &mut &mut ...>` to implement `write::WWrite`
+        //    --> src/write.rs:278:16
+        //     |
+        // 278 | impl<W: Write> WWrite for EscapingPercentEncodingWrite<W> {
+        //     |         -----  ^^^^^^     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+        //     |         |
+        //     |         unsatisfied trait bound introduced here
+        // note: required for `KeySerializerNoQuotes<EscapingPercentEncodingWrite<&mut &mut &mut &mut ...>>` to implement `tests::_::_serde::Serializer`
+        //    --> src/json.rs:562:17
+        //     |
+        // 562 | impl<W: WWrite> ser::Serializer for KeySerializerNoQuotes<W> {
+        //     |         ------  ^^^^^^^^^^^^^^^     ^^^^^^^^^^^^^^^^^^^^^^^^
+        //     |         |
+        //     |         unsatisfied trait bound introduced here
+        //
+        // In the former version, I pulled a trick to avoid work and it resulted in this (even without the trick, we'd get this)
+        // The original issue was discovered with serde_json::Value whose sheer existence is statically recursive.
+        let _ = crate::to_string(&serde_json::json!(()));
+
+        // Adding this incase to make it clearer what this is.
+        #[derive(serde::Serialize)]
+        struct NestedSerialize {
+            value: HashMap<String, NestedSerialize>,
+        }
+
+        let _ = crate::to_string(&NestedSerialize {
+            value: HashMap::new(),
+        });
+    }
+
+    /// Tests the `PercentEncoding` writer.
+    #[test]
+    fn test_percent_encoding_writer() {
+        let mut buf = String::new();
+        let mut writer = PercentEncoding::new(&mut buf);
+
+        writer.write_str("hello world").unwrap();
         assert_eq!(buf, "hello%20world");
         buf.clear();
 
@@ -534,12 +1665,65 @@ mod tests {
         assert_eq!(buf, "%5B10%2C20%2C30%5D");
     }
 
+    #[test]
+    fn test_percent_encoding_write_char() {
+        let chars = ('\0'..='\u{7FF}').chain(['\u{FFFD}', '\u{10FFFF}', '😀']);
+        for c in chars {
+            let mut by_char = PercentEncoding::new(String::new());
+            by_char.write_char(c).unwrap();
+            let mut by_str = PercentEncoding::new(String::new());
+            by_str.write_str(c.encode_utf8(&mut [0; 4])).unwrap();
+            assert_eq!(by_char.w, by_str.w, "{c:?}");
+        }
+    }
+
+    #[test]
+    fn test_percent_encoding_into_inner() {
+        let mut writer = PercentEncoding::new(String::from("a="));
+        writer.write_str("b c").unwrap();
+        writer.write_comma().unwrap();
+        assert_eq!(writer.into_inner(), "a=b%20c%2C");
+    }
+
+    /// Tests that `escape()` adds exactly one level of escaping.
+    ///
+    /// The escaped writer doesn't implement `WWrite`, so `w.escape().escape()`
+    /// doesn't compile. Only `Stringify` escapes twice, on purpose.
+    #[test]
+    fn test_escape_is_single_level() {
+        fn write_escaped(w: &mut impl WWrite) {
+            w.escape().write_str("\"\\").unwrap();
+        }
+
+        let mut out = String::new();
+        write_escaped(&mut Plain::new(&mut out));
+        assert_eq!(out, r#"\"\\"#);
+
+        let mut percent = PercentEncoding::new(String::new());
+        write_escaped(&mut percent);
+        assert_eq!(percent.into_inner(), "%5C%22%5C%5C");
+        #[cfg(feature = "dyn-write")]
+        {
+            let mut percent = PercentEncoding::new(String::new());
+            write_escaped(&mut DynWrite::new(&mut percent));
+            assert_eq!(percent.into_inner(), "%5C%22%5C%5C");
+        }
+
+        // A JSON string inside a JSON string.
+        let mut stringified = Stringify::new(PercentEncoding::new(String::new()));
+        write_escaped(&mut stringified);
+        assert_eq!(
+            stringified.into_inner().into_inner(),
+            "%5C%5C%5C%22%5C%5C%5C%5C"
+        );
+    }
+
     /// Tests the `EscapingPercentEncodingWrite` writer.
     #[test]
     fn test_escaping_percent_encoding_writer() {
         let buf = String::new();
         let mut writer = EscapingPercentEncodingWrite {
-            inner: &mut PercentEncoding { w: buf },
+            inner: &mut PercentEncoding::new(buf),
         };
 
         // Simple string needs percent encoding but no JSON escaping.
@@ -590,6 +1774,258 @@ mod tests {
         assert_eq!(buf, "%7B1%2C2%7D");
     }
 
+    /// Tests that `PercentEncoding::write_str` matches `percent_encoding` byte for byte.
+    #[test]
+    fn test_percent_encoding_batching() {
+        fn reference(s: &str) -> String {
+            percent_encoding::utf8_percent_encode(s, FORM_URLENCODING_ENCODE_SET).to_string()
+        }
+
+        // Counts the calls made to the underlying writer.
+        struct Counting(String, usize);
+
+        impl Write for Counting {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                self.1 += 1;
+                self.0.write_str(s)
+            }
+        }
+
+        let long_run = "a".repeat(BATCH_SIZE * 2 + 1);
+        let inputs = [
+            String::new(),
+            "plain".to_string(),
+            "a b&c=d".to_string(),
+            " ".repeat(BATCH_SIZE),
+            format!("x{long_run} {long_run}é"),
+            "héllo wörld 😀 ".repeat(20),
+        ];
+        for input in &inputs {
+            let mut writer = PercentEncoding::new(Counting(String::new(), 0));
+            writer.write_str(input).unwrap();
+            assert_eq!(writer.w.0, reference(input));
+
+            #[cfg(feature = "std")]
+            {
+                let mut writer = PercentEncoding::new(String::new());
+                std::io::Write::write_all(&mut writer, input.as_bytes()).unwrap();
+                assert_eq!(writer.w, reference(input));
+            }
+        }
+
+        // Every byte agrees with the reference set.
+        #[cfg(feature = "std")]
+        for byte in 0..=u8::MAX {
+            let input = [byte];
+            let mut writer = PercentEncoding::new(String::new());
+            std::io::Write::write_all(&mut writer, &input).unwrap();
+            assert_eq!(
+                writer.w,
+                percent_encoding::percent_encode(&input, FORM_URLENCODING_ENCODE_SET).to_string()
+            );
+        }
+
+        // 20 escapes and 20 letters (80 bytes) fit in a single batch.
+        let mut writer = PercentEncoding::new(Counting(String::new(), 0));
+        writer.write_str(&" a".repeat(20)).unwrap();
+        assert_eq!(writer.w.1, 1);
+    }
+
+    /// Tests every table entry against `percent_encoding` directly.
+    #[test]
+    fn test_encode_tables_match_percent_encoding() {
+        for byte in 0..=u8::MAX {
+            let expected =
+                percent_encoding::percent_encode(&[byte], FORM_URLENCODING_ENCODE_SET).to_string();
+            let entry = &PERCENT_ENCODE[usize::from(byte)];
+            assert_eq!(ascii(&entry.bytes[..usize::from(entry.len)]), expected);
+
+            // Bytes that JSON doesn't escape share the plain entry.
+            if byte >= 0x20 && byte != b'"' && byte != b'\\' {
+                let entry = &JSON_ESCAPE_PERCENT_ENCODE[usize::from(byte)];
+                assert_eq!(ascii(&entry.bytes[..usize::from(entry.len)]), expected);
+            }
+        }
+    }
+
+    /// Tests that the single-pass escaping matches `json_escape` followed by
+    /// percent-encoding.
+    #[test]
+    fn test_escaping_matches_two_pass() {
+        fn reference(s: &str) -> String {
+            let escaped = escape_str(s).to_string();
+            percent_encoding::utf8_percent_encode(&escaped, FORM_URLENCODING_ENCODE_SET).to_string()
+        }
+
+        fn escape(s: &str) -> String {
+            let mut inner = PercentEncoding::new(String::new());
+            EscapingPercentEncodingWrite::new(&mut inner)
+                .write_str(s)
+                .unwrap();
+            inner.w
+        }
+
+        // Every ASCII character, alone and between literals.
+        for c in '\0'..='\x7F' {
+            assert_eq!(escape(&c.to_string()), reference(&c.to_string()));
+            let s = format!("ab{c}cd");
+            assert_eq!(escape(&s), reference(&s));
+        }
+
+        let long_run = "a".repeat(BATCH_SIZE * 2 + 1);
+        let inputs = [
+            String::new(),
+            "\u{1}\u{b}\u{1f}\u{7f}".to_string(),
+            "\"\\\n".repeat(BATCH_SIZE),
+            format!("\"{long_run}\"{long_run}é\n"),
+            "héllo \"wörld\" 😀\r\n".repeat(20),
+        ];
+        for input in &inputs {
+            assert_eq!(escape(input), reference(input));
+        }
+    }
+
+    /// Tests the JSON escaping of the default `WWrite::escape` against `json_escape`.
+    #[test]
+    fn test_json_escaped_matches_json_escape() {
+        fn escape(s: &str) -> String {
+            let mut out = String::new();
+            write_json_escaped(&mut out, s).unwrap();
+            out
+        }
+
+        for c in '\0'..='\x7F' {
+            let s = format!("ab{c}cd{c}");
+            assert_eq!(escape(&s), escape_str(&s).to_string());
+        }
+        for s in ["", "héllo \"wörld\" 😀\r\n", "\u{1}\u{7f}\u{2028}\\"] {
+            assert_eq!(escape(s), escape_str(s).to_string());
+        }
+    }
+
+    /// Tests the pre-scan for bytes that need encoding.
+    #[test]
+    fn test_unreserved_prefix_len() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(is_unreserved_branchless(byte), is_unreserved(byte));
+        }
+
+        assert_eq!(unreserved_prefix_len(b""), 0);
+        assert_eq!(unreserved_prefix_len(b" "), 0);
+        assert_eq!(unreserved_prefix_len(b"550e8400-e29b-41d4"), 18);
+        // The first byte to encode at every position, in and after whole chunks.
+        let clean = "a".repeat(40);
+        for at in 0..=clean.len() {
+            let mut s = clean.clone();
+            s.insert(at, '%');
+            assert_eq!(unreserved_prefix_len(s.as_bytes()), at);
+        }
+    }
+
+    /// Tests that `DynWrite` writes exactly what the writer it erases writes.
+    #[cfg(feature = "dyn-write")]
+    #[test]
+    fn test_dyn_write_matches_generic() {
+        fn write_all(w: &mut impl WWrite) {
+            w.write_left_bracket().unwrap();
+            w.write_quote().unwrap();
+            w.escape().write_str("a \"b\"\n").unwrap();
+            w.write_quote().unwrap();
+            w.write_colon().unwrap();
+            w.write_integer(-12).unwrap();
+            w.write_comma().unwrap();
+            w.write_float(1.5).unwrap();
+            w.write_comma().unwrap();
+            w.write_byte_array(&[1, 2]).unwrap();
+            w.write_comma().unwrap();
+            w.write_null().unwrap();
+            w.escape().write_integer(3).unwrap();
+            w.write_right_bracket().unwrap();
+        }
+
+        let mut generic = Stringify::new(PercentEncoding::new(String::new()));
+        write_all(&mut generic);
+
+        let mut erased = Stringify::new(PercentEncoding::new(String::new()));
+        write_all(&mut DynWrite::new(&mut erased));
+        write_all(&mut erased.as_mut());
+
+        let generic = generic.into_inner().into_inner();
+        assert_eq!(erased.into_inner().into_inner(), generic.repeat(2));
+    }
+
+    /// Tests that writing chunks matches writing the joined string.
+    #[test]
+    fn test_write_str_chunks() {
+        let chunks = ["a \"quo", "ted\" ", "", "wörd\n"];
+        let joined = chunks.concat();
+
+        let mut chunked = PercentEncoding::new(String::new());
+        chunked.write_str_chunks(chunks).unwrap();
+        let mut whole = PercentEncoding::new(String::new());
+        whole.write_str(&joined).unwrap();
+        assert_eq!(chunked.w, whole.w);
+
+        let mut chunked = PercentEncoding::new(String::new());
+        EscapingPercentEncodingWrite::new(&mut chunked)
+            .write_str_chunks(chunks)
+            .unwrap();
+        let mut whole = PercentEncoding::new(String::new());
+        whole.escape().write_str(&joined).unwrap();
+        assert_eq!(chunked.w, whole.w);
+        assert_eq!(chunked.w, "a%20%5C%22quoted%5C%22%20w%C3%B6rd%5Cn");
+    }
+
+    /// Tests that `write_display` batches small pieces and spills long output.
+    #[test]
+    fn test_write_display() {
+        /// Records every `write_str` call.
+        #[derive(Default)]
+        struct Calls(Vec<String>);
+
+        impl Write for Calls {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                self.0.push(s.to_owned());
+                Ok(())
+            }
+        }
+
+        impl WWritePrimitives for Calls {}
+        impl WWrite for Calls {}
+
+        /// Writes `self.1` copies of `self.0`, one `write_str` call each.
+        struct Pieces(&'static str, usize);
+
+        impl std::fmt::Display for Pieces {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                (0..self.1).try_for_each(|_| f.write_str(self.0))
+            }
+        }
+
+        let mut calls = Calls::default();
+        calls.write_display(&Pieces("ab", 5)).unwrap();
+        assert_eq!(calls.0, ["ababababab"]);
+
+        let mut calls = Calls::default();
+        calls.write_display(&Pieces("", 3)).unwrap();
+        assert!(calls.0.is_empty());
+
+        // 30 pieces of 3 bytes: the buffer fills up after 21 of them.
+        let mut calls = Calls::default();
+        calls.write_display(&Pieces("ü.", 30)).unwrap();
+        assert_eq!(calls.0, ["ü.".repeat(21), "ü.".repeat(9)]);
+
+        // Pieces longer than the buffer go straight through.
+        let long = "x".repeat(STACK_BUFFER_SIZE + 1);
+        let mut calls = Calls::default();
+        calls.write_display(&format_args!("a{long}b")).unwrap();
+        assert_eq!(calls.0, ["a", long.as_str(), "b"]);
+
+        let mut writer = PercentEncoding::new(String::new());
+        writer.escape().write_display(&Pieces("\"\n", 40)).unwrap();
+        assert_eq!(writer.w, "%5C%22%5Cn".repeat(40));
+    }
+
     /// Tests `write_byte_array` specifically.
     #[test]
     fn test_write_byte_array() {
@@ -607,4 +2043,35 @@ mod tests {
         writer.write_byte_array(&[1, 2, 128]).unwrap();
         assert_eq!(writer.w, "%5B1%2C2%2C128%5D");
     }
+
+    /// Tests that `write_byte_array` output spanning several chunks matches
+    /// writing each number and comma on its own.
+    #[test]
+    fn test_write_byte_array_large() {
+        let value: Vec<u8> = (0..10_000u32).map(|i| (i * 37 % 256) as u8).collect();
+        let numbers: Vec<String> = value.iter().map(u8::to_string).collect();
+        let expected = format!("[{}]", numbers.join(","));
+
+        let mut plain = String::new();
+        Plain::new(&mut plain).write_byte_array(&value).unwrap();
+        assert_eq!(plain, expected);
+
+        let encoded = expected
+            .replace('[', "%5B")
+            .replace(']', "%5D")
+            .replace(',', "%2C");
+        let mut writer = PercentEncoding::new(String::new());
+        writer.write_byte_array(&value).unwrap();
+        assert_eq!(writer.w, encoded);
+
+        let mut writer = PercentEncoding::new(String::new());
+        writer.escape().write_byte_array(&value).unwrap();
+        assert_eq!(writer.w, encoded);
+
+        let mut writer = PercentEncoding::new(String::new());
+        Stringify::new(writer.as_mut())
+            .write_byte_array(&value)
+            .unwrap();
+        assert_eq!(writer.w, encoded);
+    }
 }
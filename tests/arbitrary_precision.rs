@@ -0,0 +1,56 @@
+//! `serde_json` numbers with its `arbitrary_precision` feature on.
+//!
+//! Turning the feature on changes how every `serde_json::Value` number is
+//! parsed, so it is left off for the other tests. Run these with:
+//!
+//! ```sh
+//! cargo test --features serde_json/arbitrary_precision --test arbitrary_precision -- --ignored
+//! ```
+
+#![cfg(feature = "alloc")]
+
+use std::collections::BTreeMap;
+
+use serde_metaform::{FormEncoder, error::ErrorKind, to_string};
+
+#[test]
+#[ignore = "needs the serde_json/arbitrary_precision feature"]
+fn test_top_level_numbers() {
+    let value: serde_json::Value = serde_json::from_str(
+        r#"{"id":123456789012345678901234567890,"list":[0.10000000000000000001]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        to_string(&value).unwrap(),
+        "id=123456789012345678901234567890&list=%5B0.10000000000000000001%5D"
+    );
+    assert_eq!(
+        FormEncoder::new()
+            .force_json_string_values(true)
+            .encode(&value)
+            .unwrap(),
+        "id=%22123456789012345678901234567890%22&list=%22%5B0.10000000000000000001%5D%22"
+    );
+
+    // On its own, a number is not an object.
+    let number: serde_json::Number = "123456789012345678901234567890".parse().unwrap();
+    let err = to_string(&number).unwrap_err();
+    assert!(err.matches(ErrorKind::NotAnObject));
+    assert_eq!(err.unexpected_type(), Some("Number"));
+}
+
+#[test]
+#[ignore = "needs the serde_json/arbitrary_precision feature"]
+fn test_nested_numbers() {
+    let value: serde_json::Value =
+        serde_json::from_str(r#"{"m":{"big":123456789012345678901234567890,"f":1.50}}"#).unwrap();
+    assert_eq!(
+        to_string(&value).unwrap(),
+        "m=%7B%22big%22%3A123456789012345678901234567890%2C%22f%22%3A1.50%7D"
+    );
+    let number: serde_json::Number = "-1e400".parse().unwrap();
+    assert_eq!(
+        to_string(&BTreeMap::from([("n", vec![number])])).unwrap(),
+        "n=%5B-1e400%5D"
+    );
+}